@@ -1,10 +1,10 @@
 #version 330 core
 
-out vec4 fs_color;
+in vec3 vs_color;
 
-uniform vec3 color;
+out vec4 fs_color;
 
 void main()
 {
-  fs_color = vec4(color, 1.0);
+  fs_color = vec4(vs_color, 1.0);
 }
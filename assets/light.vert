@@ -1,9 +1,14 @@
 #version 330 core
 layout(location = 0) in vec3 in_pos_in_obj_space;
+layout(location = 3) in mat4 in_pos_from_obj_to_wld_space;
+layout(location = 7) in vec3 in_color;
 
-uniform mat4 pos_from_obj_to_clp_space;
+uniform mat4 pos_from_wld_to_clp_space;
+
+out vec3 vs_color;
 
 void main()
 {
-  gl_Position = pos_from_obj_to_clp_space*vec4(in_pos_in_obj_space, 1.0);
+  gl_Position = pos_from_wld_to_clp_space*in_pos_from_obj_to_wld_space*vec4(in_pos_in_obj_space, 1.0);
+  vs_color = in_color;
 }
@@ -0,0 +1,441 @@
+extern crate gl;
+extern crate image;
+
+use gl::types::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+use cgmath::Vector2;
+use texture::{with_tight_rows, TextureFilter, TextureId, TextureTarget2D};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AtlasConfig {
+    pub width: u32,
+    pub height: u32,
+    /// Number of empty pixels between packed images and between images and
+    /// the border of the page.
+    pub padding: u32,
+    /// Number of pixels the edges of every image are repeated outwards.
+    /// Prevents neighbouring images from bleeding in when sampling with
+    /// linear filtering. Pages are uploaded without mip levels.
+    pub extrude: u32,
+}
+
+impl AtlasConfig {
+    /// The size an image occupies on a page, including extrusion and the
+    /// trailing padding.
+    fn footprint(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            width + 2 * self.extrude + self.padding,
+            height + 2 * self.extrude + self.padding,
+        )
+    }
+
+    fn fits_on_empty_page(&self, width: u32, height: u32) -> bool {
+        let (w, h) = self.footprint(width, height);
+        self.padding + w <= self.width && self.padding + h <= self.height
+    }
+}
+
+/// Texture coordinates of a packed image. The page is uploaded without
+/// flipping so `min` is the texture coordinate of the first pixel of the
+/// image and `max` that of the far corner of its last pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AtlasInsertError {
+    /// There is not enough space left on the page. Start a new page.
+    PageFull,
+    /// The image would not fit on an empty page.
+    TooLarge,
+    /// An image was already inserted with the same key.
+    DuplicateKey,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// A single atlas page packed with the skyline bottom-left heuristic.
+pub struct AtlasPage<K> {
+    config: AtlasConfig,
+    skyline: Vec<SkylineNode>,
+    image: image::RgbaImage,
+    entries: HashMap<K, UvRect>,
+    texture_id: Option<TextureId>,
+    dirty: bool,
+}
+
+impl<K: Eq + Hash> AtlasPage<K> {
+    pub fn new(config: AtlasConfig) -> Self {
+        AtlasPage {
+            config,
+            skyline: vec![
+                SkylineNode {
+                    x: config.padding,
+                    y: config.padding,
+                    width: config.width.saturating_sub(config.padding),
+                },
+            ],
+            image: image::RgbaImage::new(config.width, config.height),
+            entries: HashMap::new(),
+            texture_id: None,
+            dirty: true,
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> AtlasConfig {
+        self.config
+    }
+
+    #[inline]
+    pub fn image(&self) -> &image::RgbaImage {
+        &self.image
+    }
+
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<UvRect> {
+        self.entries.get(key).cloned()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the y coordinate at which a rectangle of the given width
+    /// can be placed when its left edge is at skyline node `index`.
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.config.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for node in &self.skyline[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = ::std::cmp::max(y, node.y);
+            remaining -= node.width as i64;
+        }
+
+        if y + height > self.config.height {
+            None
+        } else {
+            Some(y)
+        }
+    }
+
+    /// Finds the lowest position for a rectangle, preferring the leftmost
+    /// on ties. Returns the skyline node index and the y coordinate.
+    fn find(&self, width: u32, height: u32) -> Option<(usize, u32)> {
+        let mut best: Option<(usize, u32)> = None;
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                if best.map_or(true, |(_, best_y)| y < best_y) {
+                    best = Some((index, y));
+                }
+            }
+        }
+        best
+    }
+
+    fn place(&mut self, index: usize, y: u32, width: u32, height: u32) -> (u32, u32) {
+        let x = self.skyline[index].x;
+
+        self.skyline.insert(
+            index,
+            SkylineNode {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+
+        // Shrink or remove the nodes now covered by the new node.
+        let right = x + width;
+        let i = index + 1;
+        while i < self.skyline.len() {
+            let node = self.skyline[i];
+            if node.x >= right {
+                break;
+            }
+            let node_right = node.x + node.width;
+            if node_right <= right {
+                self.skyline.remove(i);
+            } else {
+                self.skyline[i].x = right;
+                self.skyline[i].width = node_right - right;
+                break;
+            }
+        }
+
+        // Merge neighbouring nodes at the same height.
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].y == self.skyline[i + 1].y {
+                self.skyline[i].width += self.skyline[i + 1].width;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+
+        (x, y)
+    }
+
+    /// Whether an image of the given size can still be inserted.
+    pub fn has_space_for(&self, width: u32, height: u32) -> bool {
+        let (w, h) = self.config.footprint(width, height);
+        self.config.fits_on_empty_page(width, height) && self.find(w, h).is_some()
+    }
+
+    /// Copies the image onto the page at (x, y) and repeats its edges
+    /// `extrude` pixels outwards.
+    fn blit(&mut self, source: &image::RgbaImage, x: u32, y: u32) {
+        let e = self.config.extrude as i64;
+        let w = source.width() as i64;
+        let h = source.height() as i64;
+
+        for dy in -e..(h + e) {
+            let sy = ::std::cmp::min(::std::cmp::max(dy, 0), h - 1) as u32;
+            for dx in -e..(w + e) {
+                let sx = ::std::cmp::min(::std::cmp::max(dx, 0), w - 1) as u32;
+                self.image.put_pixel(
+                    (x as i64 + e + dx) as u32,
+                    (y as i64 + e + dy) as u32,
+                    *source.get_pixel(sx, sy),
+                );
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: K, source: &image::RgbaImage) -> Result<UvRect, AtlasInsertError> {
+        if self.entries.contains_key(&key) {
+            return Err(AtlasInsertError::DuplicateKey);
+        }
+
+        if !self.config.fits_on_empty_page(source.width(), source.height()) {
+            return Err(AtlasInsertError::TooLarge);
+        }
+
+        let (footprint_width, footprint_height) = self.config.footprint(source.width(), source.height());
+        let (index, y) = self.find(footprint_width, footprint_height)
+            .ok_or(AtlasInsertError::PageFull)?;
+        let (x, y) = self.place(index, y, footprint_width, footprint_height);
+
+        if source.width() > 0 && source.height() > 0 {
+            self.blit(source, x, y);
+        }
+
+        let e = self.config.extrude;
+        let page_width = self.config.width as f32;
+        let page_height = self.config.height as f32;
+        let uv = UvRect {
+            min: Vector2::new(
+                (x + e) as f32 / page_width,
+                (y + e) as f32 / page_height,
+            ),
+            max: Vector2::new(
+                (x + e + source.width()) as f32 / page_width,
+                (y + e + source.height()) as f32 / page_height,
+            ),
+        };
+
+        self.entries.insert(key, uv);
+        self.dirty = true;
+
+        Ok(uv)
+    }
+
+    /// Uploads the page if anything was inserted since the last upload and
+    /// returns its texture. The texture is left bound to the given target.
+    pub fn upload(&mut self, target: &mut TextureTarget2D) -> Result<&TextureId, String> {
+        if self.texture_id.is_none() {
+            self.texture_id = Some(TextureId::new()
+                .ok_or_else(|| String::from("Failed to acquire texture id."))?);
+            self.dirty = true;
+        }
+
+        let texture_id = self.texture_id.as_ref().unwrap();
+
+        if self.dirty {
+            let mut bound = target.bind(texture_id);
+            bound
+                .min_filter(TextureFilter::Linear)
+                .mag_filter(TextureFilter::Linear)
+                .wrap_s(gl::CLAMP_TO_EDGE as GLint)
+                .wrap_t(gl::CLAMP_TO_EDGE as GLint);
+            unsafe {
+                with_tight_rows(gl::UNPACK_ALIGNMENT, || {
+                    bound.image_2d(
+                        0,
+                        gl::RGBA8 as GLint,
+                        self.image.width() as GLint,
+                        self.image.height() as GLint,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        self.image.as_ptr() as *const GLvoid,
+                    );
+                });
+            }
+            self.dirty = false;
+        }
+
+        Ok(texture_id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasEntry {
+    pub page: usize,
+    pub uv: UvRect,
+}
+
+/// A growing set of atlas pages. A new page is started whenever an image
+/// does not fit on the current one.
+pub struct Atlas<K> {
+    config: AtlasConfig,
+    pages: Vec<AtlasPage<K>>,
+}
+
+impl<K: Eq + Hash> Atlas<K> {
+    pub fn new(config: AtlasConfig) -> Self {
+        Atlas {
+            config,
+            pages: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn pages(&self) -> &[AtlasPage<K>] {
+        &self.pages
+    }
+
+    #[inline]
+    pub fn pages_mut(&mut self) -> &mut [AtlasPage<K>] {
+        &mut self.pages
+    }
+
+    pub fn get(&self, key: &K) -> Option<AtlasEntry> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter_map(|(page, p)| p.get(key).map(|uv| AtlasEntry { page, uv }))
+            .next()
+    }
+
+    pub fn insert(&mut self, key: K, source: &image::RgbaImage) -> Result<AtlasEntry, AtlasInsertError> {
+        if self.get(&key).is_some() {
+            return Err(AtlasInsertError::DuplicateKey);
+        }
+
+        if !self.config.fits_on_empty_page(source.width(), source.height()) {
+            return Err(AtlasInsertError::TooLarge);
+        }
+
+        let needs_page = self.pages
+            .last()
+            .map_or(true, |page| !page.has_space_for(source.width(), source.height()));
+
+        if needs_page {
+            self.pages.push(AtlasPage::new(self.config));
+        }
+
+        let page = self.pages.len() - 1;
+        self.pages[page]
+            .insert(key, source)
+            .map(|uv| AtlasEntry { page, uv })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AtlasConfig {
+        AtlasConfig {
+            width: 16,
+            height: 16,
+            padding: 1,
+            extrude: 1,
+        }
+    }
+
+    fn solid(width: u32, height: u32, value: u8) -> image::RgbaImage {
+        image::RgbaImage::from_pixel(width, height, image::Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn packed_images_do_not_overlap() {
+        let mut page = AtlasPage::new(config());
+        let a = page.insert("a", &solid(4, 4, 1)).unwrap();
+        let b = page.insert("b", &solid(4, 4, 2)).unwrap();
+        let c = page.insert("c", &solid(4, 4, 3)).unwrap();
+
+        let overlaps = |l: &UvRect, r: &UvRect| {
+            l.min.x < r.max.x && r.min.x < l.max.x && l.min.y < r.max.y && r.min.y < l.max.y
+        };
+        assert!(!overlaps(&a, &b));
+        assert!(!overlaps(&a, &c));
+        assert!(!overlaps(&b, &c));
+        assert_eq!(Some(b), page.get(&"b"));
+    }
+
+    #[test]
+    fn uv_rect_excludes_padding_and_extrusion() {
+        let mut page = AtlasPage::new(config());
+        let uv = page.insert(0, &solid(4, 2, 1)).unwrap();
+        assert_eq!(Vector2::new(2.0 / 16.0, 2.0 / 16.0), uv.min);
+        assert_eq!(Vector2::new(6.0 / 16.0, 4.0 / 16.0), uv.max);
+    }
+
+    #[test]
+    fn edges_are_extruded() {
+        let mut page = AtlasPage::new(config());
+        let mut source = solid(2, 2, 10);
+        source.put_pixel(0, 0, image::Rgba([20, 20, 20, 255]));
+        page.insert(0, &source).unwrap();
+
+        // The image starts at (2, 2), the extruded border at (1, 1).
+        assert_eq!(&image::Rgba([20, 20, 20, 255]), page.image().get_pixel(1, 1));
+        assert_eq!(&image::Rgba([10, 10, 10, 255]), page.image().get_pixel(4, 4));
+        assert_eq!(&image::Rgba([0, 0, 0, 0]), page.image().get_pixel(0, 0));
+    }
+
+    #[test]
+    fn reports_full_pages_and_oversized_images() {
+        let mut page = AtlasPage::new(config());
+        assert_eq!(Err(AtlasInsertError::TooLarge), page.insert(0, &solid(14, 4, 1)));
+        page.insert(1, &solid(12, 12, 1)).unwrap();
+        assert_eq!(Err(AtlasInsertError::PageFull), page.insert(2, &solid(4, 4, 1)));
+        assert_eq!(Err(AtlasInsertError::DuplicateKey), page.insert(1, &solid(1, 1, 1)));
+    }
+
+    #[test]
+    fn atlas_starts_a_new_page_when_full() {
+        let mut atlas = Atlas::new(config());
+        assert_eq!(0, atlas.insert("a", &solid(12, 12, 1)).unwrap().page);
+        assert_eq!(1, atlas.insert("b", &solid(12, 12, 1)).unwrap().page);
+        assert_eq!(1, atlas.get(&"b").unwrap().page);
+        assert_eq!(2, atlas.pages().len());
+    }
+
+    #[test]
+    fn atlas_does_not_start_a_page_for_oversized_images() {
+        let mut atlas = Atlas::new(config());
+        atlas.insert("a", &solid(12, 12, 1)).unwrap();
+        assert_eq!(Err(AtlasInsertError::TooLarge), atlas.insert("b", &solid(14, 4, 1)));
+        assert_eq!(1, atlas.pages().len());
+        assert!(atlas.get(&"b").is_none());
+    }
+}
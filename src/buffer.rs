@@ -0,0 +1,520 @@
+extern crate cgmath;
+extern crate core;
+extern crate gl;
+
+use gl::types::*;
+use id::Id;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut, Range};
+use std::ptr;
+use std::slice;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum BufferTarget {
+    ArrayBuffer = gl::ARRAY_BUFFER,
+    AtomicCounterBuffer = gl::ATOMIC_COUNTER_BUFFER,
+    CopyReadBuffer = gl::COPY_READ_BUFFER,
+    CopyWriteBuffer = gl::COPY_WRITE_BUFFER,
+    DispatchIndirectBuffer = gl::DISPATCH_INDIRECT_BUFFER,
+    DrawIndirectBuffer = gl::DRAW_INDIRECT_BUFFER,
+    ElementArrayBuffer = gl::ELEMENT_ARRAY_BUFFER,
+    PixelPackBuffer = gl::PIXEL_PACK_BUFFER,
+    PixelUnpackBuffer = gl::PIXEL_UNPACK_BUFFER,
+    QueryBuffer = gl::QUERY_BUFFER,
+    ShaderStorageBuffer = gl::SHADER_STORAGE_BUFFER,
+    TextureBuffer = gl::TEXTURE_BUFFER,
+    TransformFeedbackBuffer = gl::TRANSFORM_FEEDBACK_BUFFER,
+    UniformBuffer = gl::UNIFORM_BUFFER,
+}
+
+/// A hint about how often the data store is modified and used, and by
+/// whom.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum BufferUsage {
+    StreamDraw = gl::STREAM_DRAW,
+    StreamRead = gl::STREAM_READ,
+    StreamCopy = gl::STREAM_COPY,
+    StaticDraw = gl::STATIC_DRAW,
+    StaticRead = gl::STATIC_READ,
+    StaticCopy = gl::STATIC_COPY,
+    DynamicDraw = gl::DYNAMIC_DRAW,
+    DynamicRead = gl::DYNAMIC_READ,
+    DynamicCopy = gl::DYNAMIC_COPY,
+}
+
+#[derive(Debug)]
+pub struct BufferId(Id);
+
+impl BufferId {
+    pub unsafe fn as_uint(&self) -> GLuint {
+        (self.0).get()
+    }
+
+    pub fn new() -> Option<Self> {
+        Id::new(unsafe {
+            let mut ids: [GLuint; 1] = [0];
+            gl::GenBuffers(ids.len() as GLsizei, ids.as_mut_ptr());
+            ids[0]
+        }).map(BufferId)
+    }
+
+    pub fn bind(&self, target: BufferTarget) {
+        unsafe {
+            gl::BindBuffer(target as GLenum, self.as_uint());
+        }
+    }
+}
+
+impl Drop for BufferId {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.as_uint());
+        }
+    }
+}
+
+/// Plain old data: `Copy` types without padding for which every bit
+/// pattern is a valid value. Only these can be read back from the GPU,
+/// since nothing guarantees what the data store contains.
+pub unsafe trait Pod: Copy {}
+
+macro_rules! impl_pod {
+    ($($T:ty),*) => {
+        $(
+            unsafe impl Pod for $T {}
+        )*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+macro_rules! impl_pod_array {
+    ($($n:expr),*) => {
+        $(
+            unsafe impl<T: Pod> Pod for [T; $n] {}
+        )*
+    };
+}
+
+impl_pod_array!(1, 2, 3, 4, 8, 16);
+
+unsafe impl<T: Pod> Pod for cgmath::Vector1<T> {}
+unsafe impl<T: Pod> Pod for cgmath::Vector2<T> {}
+unsafe impl<T: Pod> Pod for cgmath::Vector3<T> {}
+unsafe impl<T: Pod> Pod for cgmath::Vector4<T> {}
+unsafe impl<T: Pod> Pod for cgmath::Matrix2<T> {}
+unsafe impl<T: Pod> Pod for cgmath::Matrix3<T> {}
+unsafe impl<T: Pod> Pod for cgmath::Matrix4<T> {}
+
+/// Checks that `count` elements starting at element `offset` lie within a
+/// buffer of `len` elements.
+fn check_range(len: usize, offset: usize, count: usize) -> Result<(), String> {
+    match offset.checked_add(count) {
+        Some(end) if end <= len => Ok(()),
+        _ => Err(format!(
+            "Elements {}..{} are out of range for a buffer of {} elements.",
+            offset,
+            offset.saturating_add(count),
+            len
+        )),
+    }
+}
+
+/// A buffer holding elements of type `T`, usable with any `BufferTarget`.
+///
+/// Uploads bind the buffer to `GL_COPY_WRITE_BUFFER` so they do not
+/// disturb the array and element array bindings used for drawing.
+#[derive(Debug)]
+pub struct Buffer<T: Copy> {
+    id: BufferId,
+    len: usize,
+    element: PhantomData<T>,
+}
+
+impl<T: Copy> Buffer<T> {
+    pub fn new() -> Result<Self, String> {
+        let id = BufferId::new().ok_or_else(|| String::from("Failed to acquire buffer id."))?;
+        Ok(Buffer {
+            id,
+            len: 0,
+            element: PhantomData,
+        })
+    }
+
+    #[inline]
+    pub fn id(&self) -> &BufferId {
+        &self.id
+    }
+
+    /// The number of elements in the data store.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn byte_len(&self) -> usize {
+        self.len * mem::size_of::<T>()
+    }
+
+    /// Replaces the data store with a copy of `data`.
+    pub fn data(&mut self, data: &[T], usage: BufferUsage) -> &mut Self {
+        self.id.bind(BufferTarget::CopyWriteBuffer);
+        unsafe {
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                usage as GLenum,
+            );
+        }
+        self.len = data.len();
+        self
+    }
+
+    /// Overwrites the elements starting at element `offset` with `data`.
+    pub fn sub_data(&mut self, offset: usize, data: &[T]) -> Result<&mut Self, String> {
+        check_range(self.len, offset, data.len())?;
+        self.id.bind(BufferTarget::CopyWriteBuffer);
+        unsafe {
+            gl::BufferSubData(
+                gl::COPY_WRITE_BUFFER,
+                (offset * mem::size_of::<T>()) as GLintptr,
+                mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Copies the elements in `src_range` of `src` to this buffer, starting
+    /// at element `dst_offset`. The copy is performed on the GPU.
+    pub fn copy_from(
+        &mut self,
+        dst_offset: usize,
+        src: &Buffer<T>,
+        src_range: Range<usize>,
+    ) -> Result<&mut Self, String> {
+        check_copy_ranges(src.len, &src_range, self.len, dst_offset)?;
+        src.id.bind(BufferTarget::CopyReadBuffer);
+        self.id.bind(BufferTarget::CopyWriteBuffer);
+        unsafe {
+            copy_buffer_sub_data::<T>(src_range.start, dst_offset, src_range.end - src_range.start);
+        }
+        Ok(self)
+    }
+
+    /// Copies the elements in `src_range` to element `dst_offset` of the same
+    /// buffer. The ranges may not overlap.
+    pub fn copy_within(
+        &mut self,
+        src_range: Range<usize>,
+        dst_offset: usize,
+    ) -> Result<&mut Self, String> {
+        check_copy_ranges(self.len, &src_range, self.len, dst_offset)?;
+        let count = src_range.end - src_range.start;
+        if src_range.start < dst_offset + count && dst_offset < src_range.end {
+            return Err(format!(
+                "Can not copy elements {}..{} onto the overlapping elements {}..{}.",
+                src_range.start,
+                src_range.end,
+                dst_offset,
+                dst_offset + count
+            ));
+        }
+        self.id.bind(BufferTarget::CopyReadBuffer);
+        self.id.bind(BufferTarget::CopyWriteBuffer);
+        unsafe {
+            copy_buffer_sub_data::<T>(src_range.start, dst_offset, count);
+        }
+        Ok(self)
+    }
+
+    /// Maps the elements in `range` for writing with the given `flags`. The
+    /// buffer is unmapped when the returned guard is dropped.
+    ///
+    /// The guard only derefs to the mapped elements when `T: Pod`, because
+    /// with `MapFlags::INVALIDATE_RANGE` or `MapFlags::INVALIDATE_BUFFER`
+    /// they are undefined until written. Other types are written with
+    /// `MappedBufferMut::write`.
+    pub fn map_write(
+        &mut self,
+        range: Range<usize>,
+        flags: MapFlags,
+    ) -> Result<MappedBufferMut<T>, String> {
+        let ptr = self.map_range(&range, gl::MAP_WRITE_BIT | flags.0)?;
+        Ok(MappedBufferMut {
+            buffer: self,
+            ptr,
+            len: range.end - range.start,
+            flush_explicit: flags.contains(MapFlags::FLUSH_EXPLICIT),
+        })
+    }
+
+    fn map_range(&mut self, range: &Range<usize>, access: GLbitfield) -> Result<*mut T, String> {
+        check_map_range(self.len, range)?;
+        self.id.bind(BufferTarget::CopyWriteBuffer);
+        let ptr = unsafe {
+            gl::MapBufferRange(
+                gl::COPY_WRITE_BUFFER,
+                (range.start * mem::size_of::<T>()) as GLintptr,
+                ((range.end - range.start) * mem::size_of::<T>()) as GLsizeiptr,
+                access,
+            )
+        };
+        if ptr.is_null() {
+            Err(format!(
+                "Failed to map elements {}..{} of buffer {}.",
+                range.start,
+                range.end,
+                unsafe { self.id.as_uint() }
+            ))
+        } else {
+            Ok(ptr as *mut T)
+        }
+    }
+
+    fn unmap(&mut self) {
+        self.id.bind(BufferTarget::CopyWriteBuffer);
+        unsafe {
+            // A false return value means the data store was corrupted while
+            // mapped, for example by a display mode change. There is nothing
+            // sensible to do about that in a drop.
+            gl::UnmapBuffer(gl::COPY_WRITE_BUFFER);
+        }
+    }
+}
+
+impl<T: Pod> Buffer<T> {
+    /// Reads the elements in `range` back from the GPU. Waits for all
+    /// pending commands that write to the buffer.
+    pub fn read_back(&self, range: Range<usize>) -> Result<Vec<T>, String> {
+        if range.start > range.end {
+            return Err(format!("Invalid range {}..{}.", range.start, range.end));
+        }
+        let count = range.end - range.start;
+        check_range(self.len, range.start, count)?;
+        self.id.bind(BufferTarget::CopyReadBuffer);
+        let mut elements: Vec<T> = Vec::with_capacity(count);
+        unsafe {
+            gl::GetBufferSubData(
+                gl::COPY_READ_BUFFER,
+                (range.start * mem::size_of::<T>()) as GLintptr,
+                (count * mem::size_of::<T>()) as GLsizeiptr,
+                elements.as_mut_ptr() as *mut GLvoid,
+            );
+            elements.set_len(count);
+        }
+        Ok(elements)
+    }
+
+    /// Maps the elements in `range` for reading. The buffer is unmapped when
+    /// the returned guard is dropped.
+    pub fn map_read(&mut self, range: Range<usize>) -> Result<MappedBuffer<T>, String> {
+        let ptr = self.map_range(&range, gl::MAP_READ_BIT)?;
+        Ok(MappedBuffer {
+            buffer: self,
+            ptr,
+            len: range.end - range.start,
+        })
+    }
+}
+
+fn check_copy_ranges(
+    src_len: usize,
+    src_range: &Range<usize>,
+    dst_len: usize,
+    dst_offset: usize,
+) -> Result<(), String> {
+    if src_range.start > src_range.end {
+        return Err(format!(
+            "Invalid range {}..{}.",
+            src_range.start, src_range.end
+        ));
+    }
+    let count = src_range.end - src_range.start;
+    check_range(src_len, src_range.start, count)?;
+    check_range(dst_len, dst_offset, count)
+}
+
+/// Copies `count` elements of `T` from the buffer bound to
+/// `GL_COPY_READ_BUFFER` to the buffer bound to `GL_COPY_WRITE_BUFFER`.
+unsafe fn copy_buffer_sub_data<T>(src_offset: usize, dst_offset: usize, count: usize) {
+    gl::CopyBufferSubData(
+        gl::COPY_READ_BUFFER,
+        gl::COPY_WRITE_BUFFER,
+        (src_offset * mem::size_of::<T>()) as GLintptr,
+        (dst_offset * mem::size_of::<T>()) as GLintptr,
+        (count * mem::size_of::<T>()) as GLsizeiptr,
+    );
+}
+
+/// Additional flags for `Buffer::map_write`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MapFlags(GLbitfield);
+
+impl MapFlags {
+    pub const NONE: MapFlags = MapFlags(0);
+    /// The previous contents of the mapped range may be discarded.
+    pub const INVALIDATE_RANGE: MapFlags = MapFlags(gl::MAP_INVALIDATE_RANGE_BIT);
+    /// The previous contents of the entire buffer may be discarded.
+    pub const INVALIDATE_BUFFER: MapFlags = MapFlags(gl::MAP_INVALIDATE_BUFFER_BIT);
+    /// Do not wait for pending operations on the buffer. The caller is
+    /// responsible for not overwriting data that is still in use.
+    pub const UNSYNCHRONIZED: MapFlags = MapFlags(gl::MAP_UNSYNCHRONIZED_BIT);
+    /// Modifications are only made visible by `MappedBufferMut::flush`.
+    pub const FLUSH_EXPLICIT: MapFlags = MapFlags(gl::MAP_FLUSH_EXPLICIT_BIT);
+
+    #[inline]
+    pub fn contains(&self, other: MapFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ::std::ops::BitOr for MapFlags {
+    type Output = MapFlags;
+
+    #[inline]
+    fn bitor(self, other: MapFlags) -> MapFlags {
+        MapFlags(self.0 | other.0)
+    }
+}
+
+fn check_map_range(len: usize, range: &Range<usize>) -> Result<(), String> {
+    if range.start >= range.end {
+        return Err(format!(
+            "Can not map the empty range {}..{}.",
+            range.start, range.end
+        ));
+    }
+    check_range(len, range.start, range.end - range.start)
+}
+
+/// A range of a buffer mapped for reading. Unmaps the buffer when dropped.
+pub struct MappedBuffer<'b, T: Copy + 'b> {
+    buffer: &'b mut Buffer<T>,
+    ptr: *mut T,
+    len: usize,
+}
+
+impl<'b, T: Pod> Deref for MappedBuffer<'b, T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'b, T: Copy> Drop for MappedBuffer<'b, T> {
+    fn drop(&mut self) {
+        self.buffer.unmap();
+    }
+}
+
+/// A range of a buffer mapped for writing. Unmaps the buffer when dropped.
+pub struct MappedBufferMut<'b, T: Copy + 'b> {
+    buffer: &'b mut Buffer<T>,
+    ptr: *mut T,
+    len: usize,
+    flush_explicit: bool,
+}
+
+impl<'b, T: Copy> MappedBufferMut<'b, T> {
+    /// Overwrites the mapped elements starting at `offset`, relative to the
+    /// start of the mapping, with `data`.
+    pub fn write(&mut self, offset: usize, data: &[T]) -> Result<&mut Self, String> {
+        check_range(self.len, offset, data.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), self.ptr.add(offset), data.len());
+        }
+        Ok(self)
+    }
+
+    /// Makes the modifications to `range`, relative to the start of the
+    /// mapping, visible. Only valid when mapped with
+    /// `MapFlags::FLUSH_EXPLICIT`.
+    pub fn flush(&mut self, range: Range<usize>) -> Result<&mut Self, String> {
+        if !self.flush_explicit {
+            return Err(String::from(
+                "Can only flush buffers mapped with MapFlags::FLUSH_EXPLICIT.",
+            ));
+        }
+        check_map_range(self.len, &range)?;
+        self.buffer.id.bind(BufferTarget::CopyWriteBuffer);
+        unsafe {
+            gl::FlushMappedBufferRange(
+                gl::COPY_WRITE_BUFFER,
+                (range.start * mem::size_of::<T>()) as GLintptr,
+                ((range.end - range.start) * mem::size_of::<T>()) as GLsizeiptr,
+            );
+        }
+        Ok(self)
+    }
+}
+
+impl<'b, T: Pod> Deref for MappedBufferMut<'b, T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'b, T: Pod> DerefMut for MappedBufferMut<'b, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<'b, T: Copy> Drop for MappedBufferMut<'b, T> {
+    fn drop(&mut self) {
+        self.buffer.unmap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferId;
+    use super::{check_copy_ranges, check_map_range, check_range};
+
+    #[test]
+    fn buffer_id_has_size_4() {
+        assert_eq!(4, ::std::mem::size_of::<BufferId>());
+        assert_eq!(4, ::std::mem::size_of::<Option<BufferId>>());
+    }
+
+    #[test]
+    fn ranges_are_checked() {
+        assert!(check_range(4, 0, 4).is_ok());
+        assert!(check_range(4, 4, 0).is_ok());
+        assert!(check_range(4, 3, 2).is_err());
+        assert!(check_range(4, ::std::usize::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn map_ranges_must_be_non_empty() {
+        assert!(check_map_range(4, &(0..4)).is_ok());
+        assert!(check_map_range(4, &(2..2)).is_err());
+        assert!(check_map_range(4, &(3..1)).is_err());
+        assert!(check_map_range(4, &(3..5)).is_err());
+    }
+
+    #[test]
+    fn copy_ranges_are_checked_on_both_ends() {
+        assert!(check_copy_ranges(8, &(2..6), 4, 0).is_ok());
+        assert!(check_copy_ranges(8, &(2..6), 4, 1).is_err());
+        assert!(check_copy_ranges(4, &(2..6), 8, 0).is_err());
+        assert!(check_copy_ranges(8, &(6..2), 8, 0).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+extern crate gl;
+
+use gl::types::*;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use framebuffer::{DrawFramebufferSlot, ReadFramebufferSlot};
+use program::ProgramSlot;
+use render_state::RenderStateApplier;
+use renderbuffer::RenderbufferSlot;
+use texture::TextureUnitSlot;
+
+static CREATED: AtomicBool = AtomicBool::new(false);
+
+/// Implementation limits queried when the context is created.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    pub max_texture_size: GLint,
+    pub max_3d_texture_size: GLint,
+    pub max_array_texture_layers: GLint,
+    pub max_renderbuffer_size: GLint,
+    pub max_combined_texture_image_units: GLint,
+    pub max_vertex_attribs: GLint,
+    pub max_color_attachments: GLint,
+    pub max_draw_buffers: GLint,
+    pub max_samples: GLint,
+    pub uniform_buffer_offset_alignment: GLint,
+}
+
+impl Limits {
+    fn query() -> Self {
+        let get = |pname: GLenum| {
+            let mut value: GLint = 0;
+            unsafe {
+                gl::GetIntegerv(pname, &mut value);
+            }
+            value
+        };
+
+        Limits {
+            max_texture_size: get(gl::MAX_TEXTURE_SIZE),
+            max_3d_texture_size: get(gl::MAX_3D_TEXTURE_SIZE),
+            max_array_texture_layers: get(gl::MAX_ARRAY_TEXTURE_LAYERS),
+            max_renderbuffer_size: get(gl::MAX_RENDERBUFFER_SIZE),
+            max_combined_texture_image_units: get(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+            max_vertex_attribs: get(gl::MAX_VERTEX_ATTRIBS),
+            max_color_attachments: get(gl::MAX_COLOR_ATTACHMENTS),
+            max_draw_buffers: get(gl::MAX_DRAW_BUFFERS),
+            max_samples: get(gl::MAX_SAMPLES),
+            uniform_buffer_offset_alignment: get(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT),
+        }
+    }
+}
+
+/// The context version and the extensions it supports.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+    pub major_version: GLint,
+    pub minor_version: GLint,
+    pub extensions: Vec<String>,
+}
+
+impl Capabilities {
+    fn query() -> Self {
+        unsafe {
+            let mut major_version: GLint = 0;
+            let mut minor_version: GLint = 0;
+            let mut count: GLint = 0;
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major_version);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor_version);
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+
+            let extensions = (0..count as GLuint)
+                .map(|index| {
+                    let name = gl::GetStringi(gl::EXTENSIONS, index);
+                    CStr::from_ptr(name as *const _).to_string_lossy().into_owned()
+                })
+                .collect();
+
+            Capabilities {
+                major_version,
+                minor_version,
+                extensions,
+            }
+        }
+    }
+
+    /// Whether the context version is at least `major.minor`.
+    #[inline]
+    pub fn has_version(&self, major: GLint, minor: GLint) -> bool {
+        (self.major_version, self.minor_version) >= (major, minor)
+    }
+
+    /// Whether the named extension, like `"GL_ARB_buffer_storage"`, is
+    /// supported.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Whether immutable buffer storage and persistent mapping are
+    /// available, either in core or through `ARB_buffer_storage`.
+    #[inline]
+    pub fn has_buffer_storage(&self) -> bool {
+        self.has_version(4, 4) || self.has_extension("GL_ARB_buffer_storage")
+    }
+
+    /// Whether `glDrawElementsIndirect` is available.
+    #[inline]
+    pub fn has_draw_indirect(&self) -> bool {
+        self.has_version(4, 0) || self.has_extension("GL_ARB_draw_indirect")
+    }
+
+    /// Whether `glMultiDrawElementsIndirect` is available.
+    #[inline]
+    pub fn has_multi_draw_indirect(&self) -> bool {
+        self.has_version(4, 3) || self.has_extension("GL_ARB_multi_draw_indirect")
+    }
+
+    /// Whether vertex formats can be specified separately from the vertex
+    /// buffers they read from.
+    #[inline]
+    pub fn has_vertex_attrib_binding(&self) -> bool {
+        self.has_version(4, 3) || self.has_extension("GL_ARB_vertex_attrib_binding")
+    }
+
+    /// Whether draws can start at a base instance other than zero.
+    #[inline]
+    pub fn has_base_instance(&self) -> bool {
+        self.has_version(4, 2) || self.has_extension("GL_ARB_base_instance")
+    }
+}
+
+/// Owns every binding slot of the current OpenGL context. Only one can be
+/// created per process, so the slots can not be duplicated and the
+/// borrow-based binding guarantees hold.
+pub struct GlContext {
+    pub texture_unit_slot: TextureUnitSlot,
+    pub program_slot: ProgramSlot,
+    pub renderbuffer_slot: RenderbufferSlot,
+    pub draw_framebuffer_slot: DrawFramebufferSlot,
+    pub read_framebuffer_slot: ReadFramebufferSlot,
+    pub render_state: RenderStateApplier,
+    limits: Limits,
+    capabilities: Capabilities,
+    // OpenGL contexts are bound to the thread they are current on.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl GlContext {
+    /// Loads the OpenGL function pointers with `get_proc_address` and
+    /// queries the implementation limits and capabilities. The window's context must be
+    /// current, for example:
+    ///
+    /// ```ignore
+    /// unsafe { gl_window.make_current().unwrap() };
+    /// let context = GlContext::new(|symbol| gl_window.get_proc_address(symbol) as *const _);
+    /// ```
+    pub fn new<F>(get_proc_address: F) -> Result<Self, String>
+    where
+        F: FnMut(&str) -> *const c_void,
+    {
+        if CREATED.swap(true, Ordering::SeqCst) {
+            return Err(String::from("A GlContext has already been created."));
+        }
+
+        gl::load_with(get_proc_address);
+
+        unsafe {
+            Ok(GlContext {
+                texture_unit_slot: TextureUnitSlot::new(),
+                program_slot: ProgramSlot::new(),
+                renderbuffer_slot: RenderbufferSlot::new(),
+                draw_framebuffer_slot: DrawFramebufferSlot::new(),
+                read_framebuffer_slot: ReadFramebufferSlot::new(),
+                render_state: RenderStateApplier::new(),
+                limits: Limits::query(),
+                capabilities: Capabilities::query(),
+                _not_send: PhantomData,
+            })
+        }
+    }
+
+    #[inline]
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    #[inline]
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Capabilities;
+
+    #[test]
+    fn capabilities_compare_versions_and_extensions() {
+        let capabilities = Capabilities {
+            major_version: 3,
+            minor_version: 3,
+            extensions: vec![String::from("GL_ARB_buffer_storage")],
+        };
+        assert!(capabilities.has_version(3, 3));
+        assert!(capabilities.has_version(2, 1));
+        assert!(!capabilities.has_version(4, 0));
+        assert!(capabilities.has_buffer_storage());
+        assert!(!capabilities.has_extension("GL_ARB_multi_draw_indirect"));
+    }
+}
@@ -0,0 +1,42 @@
+extern crate gl;
+
+use gl::types::*;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum DrawMode {
+    Points = gl::POINTS,
+    LineStrip = gl::LINE_STRIP,
+    LineLoop = gl::LINE_LOOP,
+    Lines = gl::LINES,
+    LineStripAdjacency = gl::LINE_STRIP_ADJACENCY,
+    LinesAdjacency = gl::LINES_ADJACENCY,
+    TriangleStrip = gl::TRIANGLE_STRIP,
+    TriangleFan = gl::TRIANGLE_FAN,
+    Triangles = gl::TRIANGLES,
+    TriangleStripAdjacency = gl::TRIANGLE_STRIP_ADJACENCY,
+    TrianglesAdjacency = gl::TRIANGLES_ADJACENCY,
+    Patches = gl::PATCHES,
+}
+
+/// Draws the vertices in `range` of the bound vertex array. Unsafe because
+/// the vertex array is not checked to hold that many vertices.
+pub unsafe fn draw_arrays(mode: DrawMode, range: Range<usize>) {
+    gl::DrawArrays(
+        mode as GLenum,
+        range.start as GLint,
+        (range.end - range.start) as GLsizei,
+    );
+}
+
+/// Draws `instance_count` instances of the vertices in `range` of the bound
+/// vertex array. Attributes with a non-zero divisor advance per instance.
+pub unsafe fn draw_arrays_instanced(mode: DrawMode, range: Range<usize>, instance_count: usize) {
+    gl::DrawArraysInstanced(
+        mode as GLenum,
+        range.start as GLint,
+        (range.end - range.start) as GLsizei,
+        instance_count as GLsizei,
+    );
+}
@@ -0,0 +1,52 @@
+extern crate gl;
+
+use gl::types::*;
+
+/// A fence sync object, signaled once the GPU has executed all commands
+/// issued before it.
+#[derive(Debug)]
+pub struct Fence(GLsync);
+
+impl Fence {
+    /// Inserts a fence into the command stream.
+    pub fn new() -> Result<Self, String> {
+        let sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        if sync.is_null() {
+            Err(String::from("Failed to create fence sync."))
+        } else {
+            Ok(Fence(sync))
+        }
+    }
+
+    /// Whether the fence has been signaled, without waiting.
+    pub fn is_signaled(&self) -> Result<bool, String> {
+        self.client_wait(0, 0)
+    }
+
+    /// Blocks until the fence is signaled. Pending commands are flushed so
+    /// the fence is guaranteed to signal eventually.
+    pub fn wait(&self) -> Result<(), String> {
+        const TIMEOUT_NS: GLuint64 = 1_000_000;
+        let mut flags = gl::SYNC_FLUSH_COMMANDS_BIT;
+        while !self.client_wait(flags, TIMEOUT_NS)? {
+            flags = 0;
+        }
+        Ok(())
+    }
+
+    fn client_wait(&self, flags: GLbitfield, timeout: GLuint64) -> Result<bool, String> {
+        match unsafe { gl::ClientWaitSync(self.0, flags, timeout) } {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => Ok(true),
+            gl::TIMEOUT_EXPIRED => Ok(false),
+            _ => Err(String::from("Failed to wait for fence sync.")),
+        }
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.0);
+        }
+    }
+}
@@ -1,13 +1,14 @@
-#![allow(non_snake_case)]
-
 extern crate core;
 extern crate gl;
+extern crate image;
 
 use id::Id;
 use gl::types::*;
 use std::marker::PhantomData;
-use renderbuffer::RenderbufferId;
-use texture::TextureId;
+use renderbuffer::{BoundRenderbufferId, RenderbufferFormatKind, RenderbufferInternalFormat};
+use texture::{BoundTextureId, CubeMapFace, TexturePixel, TextureTarget, TextureTargetCubeMap,
+              TextureTargetFramebuffer2D, TextureTargetFramebufferLayer,
+              TextureTargetFramebufferLayered, with_tight_rows};
 use phantomdata::into_phantom_data;
 
 pub trait MaybeDefaultFramebufferId {
@@ -61,8 +62,8 @@ impl MaybeDefaultFramebufferId for FramebufferId {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
-#[non_exhaustive]
 pub enum FramebufferAttachment {
     Color0 = gl::COLOR_ATTACHMENT0,
     Color1 = gl::COLOR_ATTACHMENT1,
@@ -72,44 +73,734 @@ pub enum FramebufferAttachment {
     Color5 = gl::COLOR_ATTACHMENT5,
     Color6 = gl::COLOR_ATTACHMENT6,
     Color7 = gl::COLOR_ATTACHMENT7,
-    // Additional colors can be constructed with FramebufferAttachment::color(index: u32).
+    Color8 = gl::COLOR_ATTACHMENT8,
+    Color9 = gl::COLOR_ATTACHMENT9,
+    Color10 = gl::COLOR_ATTACHMENT10,
+    Color11 = gl::COLOR_ATTACHMENT11,
+    Color12 = gl::COLOR_ATTACHMENT12,
+    Color13 = gl::COLOR_ATTACHMENT13,
+    Color14 = gl::COLOR_ATTACHMENT14,
+    Color15 = gl::COLOR_ATTACHMENT15,
+    Color16 = gl::COLOR_ATTACHMENT16,
+    Color17 = gl::COLOR_ATTACHMENT17,
+    Color18 = gl::COLOR_ATTACHMENT18,
+    Color19 = gl::COLOR_ATTACHMENT19,
+    Color20 = gl::COLOR_ATTACHMENT20,
+    Color21 = gl::COLOR_ATTACHMENT21,
+    Color22 = gl::COLOR_ATTACHMENT22,
+    Color23 = gl::COLOR_ATTACHMENT23,
+    Color24 = gl::COLOR_ATTACHMENT24,
+    Color25 = gl::COLOR_ATTACHMENT25,
+    Color26 = gl::COLOR_ATTACHMENT26,
+    Color27 = gl::COLOR_ATTACHMENT27,
+    Color28 = gl::COLOR_ATTACHMENT28,
+    Color29 = gl::COLOR_ATTACHMENT29,
+    Color30 = gl::COLOR_ATTACHMENT30,
+    Color31 = gl::COLOR_ATTACHMENT31,
     Depth = gl::DEPTH_ATTACHMENT,
     Stencil = gl::STENCIL_ATTACHMENT,
     DepthStencil = gl::DEPTH_STENCIL_ATTACHMENT,
 }
 
+/// OpenGL defines enums for 32 color attachments. Implementations may
+/// support fewer, see `GL_MAX_COLOR_ATTACHMENTS`.
+const COLOR_ATTACHMENTS: [FramebufferAttachment; 32] = [
+    FramebufferAttachment::Color0,
+    FramebufferAttachment::Color1,
+    FramebufferAttachment::Color2,
+    FramebufferAttachment::Color3,
+    FramebufferAttachment::Color4,
+    FramebufferAttachment::Color5,
+    FramebufferAttachment::Color6,
+    FramebufferAttachment::Color7,
+    FramebufferAttachment::Color8,
+    FramebufferAttachment::Color9,
+    FramebufferAttachment::Color10,
+    FramebufferAttachment::Color11,
+    FramebufferAttachment::Color12,
+    FramebufferAttachment::Color13,
+    FramebufferAttachment::Color14,
+    FramebufferAttachment::Color15,
+    FramebufferAttachment::Color16,
+    FramebufferAttachment::Color17,
+    FramebufferAttachment::Color18,
+    FramebufferAttachment::Color19,
+    FramebufferAttachment::Color20,
+    FramebufferAttachment::Color21,
+    FramebufferAttachment::Color22,
+    FramebufferAttachment::Color23,
+    FramebufferAttachment::Color24,
+    FramebufferAttachment::Color25,
+    FramebufferAttachment::Color26,
+    FramebufferAttachment::Color27,
+    FramebufferAttachment::Color28,
+    FramebufferAttachment::Color29,
+    FramebufferAttachment::Color30,
+    FramebufferAttachment::Color31,
+];
+
 impl FramebufferAttachment {
+    /// Returns `None` when there is no color attachment enum for `index`.
     #[inline]
-    pub fn color(index: u32) -> Self {
-        // TODO: Is this function unsafe? What happens when you do color(1234123412341234) and then use that?
-        unsafe {
-            ::std::mem::transmute::<u32, FramebufferAttachment>(gl::COLOR_ATTACHMENT0 + index)
+    pub fn color(index: u32) -> Option<Self> {
+        COLOR_ATTACHMENTS.get(index as usize).cloned()
+    }
+
+    #[inline]
+    pub fn from_enum(value: GLenum) -> Option<Self> {
+        match value {
+            gl::DEPTH_ATTACHMENT => Some(FramebufferAttachment::Depth),
+            gl::STENCIL_ATTACHMENT => Some(FramebufferAttachment::Stencil),
+            gl::DEPTH_STENCIL_ATTACHMENT => Some(FramebufferAttachment::DepthStencil),
+            _ => value
+                .checked_sub(gl::COLOR_ATTACHMENT0)
+                .and_then(FramebufferAttachment::color),
+        }
+    }
+
+    /// Whether images of the given kind can be attached here. Depth and
+    /// stencil attachment points also accept combined depth stencil
+    /// formats.
+    pub fn accepts(&self, kind: RenderbufferFormatKind) -> bool {
+        match (*self, kind) {
+            (FramebufferAttachment::Depth, RenderbufferFormatKind::Depth)
+            | (FramebufferAttachment::Depth, RenderbufferFormatKind::DepthStencil)
+            | (FramebufferAttachment::Stencil, RenderbufferFormatKind::Stencil)
+            | (FramebufferAttachment::Stencil, RenderbufferFormatKind::DepthStencil)
+            | (FramebufferAttachment::DepthStencil, RenderbufferFormatKind::DepthStencil) => true,
+            (attachment, RenderbufferFormatKind::Color) => attachment.color_index().is_some(),
+            _ => false,
+        }
+    }
+
+    /// The index of a color attachment.
+    #[inline]
+    pub fn color_index(&self) -> Option<u32> {
+        match *self {
+            FramebufferAttachment::Depth
+            | FramebufferAttachment::Stencil
+            | FramebufferAttachment::DepthStencil => None,
+            _ => Some(*self as u32 - gl::COLOR_ATTACHMENT0),
         }
     }
 }
 
+/// The reason `glCheckFramebufferStatus` reported a framebuffer as
+/// incomplete. Where it can be determined, the offending attachment is
+/// included.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FramebufferIncomplete {
+    /// The default framebuffer is bound but does not exist.
+    Undefined,
+    /// An attachment is not framebuffer complete, for example because it
+    /// has a zero size or a format that can not be rendered to at its
+    /// attachment point.
+    IncompleteAttachment(Option<FramebufferAttachment>),
+    /// No images are attached.
+    MissingAttachment,
+    /// A draw buffer names an attachment point without an image.
+    IncompleteDrawBuffer(Option<FramebufferAttachment>),
+    /// The read buffer names an attachment point without an image.
+    IncompleteReadBuffer(Option<FramebufferAttachment>),
+    /// The combination of internal formats is not supported by the
+    /// implementation.
+    Unsupported,
+    /// The attachments do not have the same number of samples or fixed
+    /// sample locations.
+    IncompleteMultisample,
+    /// Some attachments are layered and others are not, or the layered
+    /// attachments have different targets.
+    IncompleteLayerTargets,
+    /// A status not known to this wrapper, or 0 when an error occurred.
+    Unknown(GLenum),
+}
+
+impl ::std::fmt::Display for FramebufferIncomplete {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            FramebufferIncomplete::Undefined => write!(f, "the default framebuffer does not exist"),
+            FramebufferIncomplete::IncompleteAttachment(Some(a)) => {
+                write!(f, "attachment {:?} is incomplete", a)
+            }
+            FramebufferIncomplete::IncompleteAttachment(None) => {
+                write!(f, "an attachment is incomplete")
+            }
+            FramebufferIncomplete::MissingAttachment => write!(f, "no images are attached"),
+            FramebufferIncomplete::IncompleteDrawBuffer(Some(a)) => {
+                write!(f, "draw buffer {:?} has no image attached", a)
+            }
+            FramebufferIncomplete::IncompleteDrawBuffer(None) => {
+                write!(f, "a draw buffer has no image attached")
+            }
+            FramebufferIncomplete::IncompleteReadBuffer(Some(a)) => {
+                write!(f, "read buffer {:?} has no image attached", a)
+            }
+            FramebufferIncomplete::IncompleteReadBuffer(None) => {
+                write!(f, "the read buffer has no image attached")
+            }
+            FramebufferIncomplete::Unsupported => {
+                write!(f, "the combination of attachment formats is unsupported")
+            }
+            FramebufferIncomplete::IncompleteMultisample => {
+                write!(f, "the attachments have different sample counts")
+            }
+            FramebufferIncomplete::IncompleteLayerTargets => {
+                write!(f, "the attachments have different layer targets")
+            }
+            FramebufferIncomplete::Unknown(status) => {
+                write!(f, "unknown framebuffer status 0x{:X}", status)
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for FramebufferIncomplete {}
+
+/// The reason a texture image was not attached to a framebuffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AttachmentError {
+    /// The texture is not bound to the target of the bound texture,
+    /// because it was created with a different target.
+    TargetMismatch,
+    /// The mipmap level has not been specified.
+    MissingLevel(GLint),
+    /// The layer does not exist at the mipmap level.
+    LayerOutOfRange { layer: GLint, layers: GLint },
+    /// The format can not be attached at the attachment point, for example
+    /// a color format at the depth attachment.
+    IncompatibleFormat(RenderbufferInternalFormat),
+}
+
+impl ::std::fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            AttachmentError::TargetMismatch => {
+                write!(f, "the texture was created with a different target")
+            }
+            AttachmentError::MissingLevel(level) => {
+                write!(f, "mipmap level {} has not been specified", level)
+            }
+            AttachmentError::LayerOutOfRange { layer, layers } => {
+                write!(f, "layer {} is out of range for {} layers", layer, layers)
+            }
+            AttachmentError::IncompatibleFormat(format) => {
+                write!(f, "{:?} can not be attached at this attachment point", format)
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for AttachmentError {}
+
+/// Checks a mipmap level of size `(width, height, depth)` and an optional
+/// layer. Textures without layers have a depth of 1.
+fn check_level_and_layer(
+    level: GLint,
+    (width, height, depth): (GLsizei, GLsizei, GLsizei),
+    layer: Option<GLint>,
+) -> Result<(), AttachmentError> {
+    if level < 0 || width == 0 || height == 0 || depth == 0 {
+        return Err(AttachmentError::MissingLevel(level));
+    }
+    match layer {
+        Some(layer) if layer < 0 || layer >= depth => Err(AttachmentError::LayerOutOfRange {
+            layer,
+            layers: depth,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn check_texture<'t, 'i, TTextureTarget: 't + TextureTarget>(
+    texture: &BoundTextureId<'t, 'i, TTextureTarget>,
+    level: GLint,
+    layer: Option<GLint>,
+) -> Result<(), AttachmentError> {
+    if !texture.is_bound() {
+        return Err(AttachmentError::TargetMismatch);
+    }
+    if level < 0 {
+        return Err(AttachmentError::MissingLevel(level));
+    }
+    let (width, height, depth) = texture.level_size(level);
+    // 1D array textures store their layers along the height.
+    let (height, depth) = if texture.target_as_enum() == gl::TEXTURE_1D_ARRAY {
+        (::std::cmp::min(height, 1), height)
+    } else {
+        (height, depth)
+    };
+    check_level_and_layer(level, (width, height, depth), layer)
+}
+
+/// A rectangle in window coordinates, with the origin in the lower left
+/// corner.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rect {
+    pub x: GLint,
+    pub y: GLint,
+    pub width: GLsizei,
+    pub height: GLsizei,
+}
+
+impl Rect {
+    #[inline]
+    pub fn new(x: GLint, y: GLint, width: GLsizei, height: GLsizei) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    /// A rectangle at the origin.
+    #[inline]
+    pub fn from_size(width: GLsizei, height: GLsizei) -> Self {
+        Rect::new(0, 0, width, height)
+    }
+
+    #[inline]
+    fn same_size(&self, other: &Rect) -> bool {
+        self.width == other.width && self.height == other.height
+    }
+}
+
+/// The buffers copied by a blit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlitMask(GLbitfield);
+
+impl BlitMask {
+    pub const COLOR: BlitMask = BlitMask(gl::COLOR_BUFFER_BIT);
+    pub const DEPTH: BlitMask = BlitMask(gl::DEPTH_BUFFER_BIT);
+    pub const STENCIL: BlitMask = BlitMask(gl::STENCIL_BUFFER_BIT);
+
+    #[inline]
+    pub fn contains(&self, other: BlitMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl ::std::ops::BitOr for BlitMask {
+    type Output = BlitMask;
+
+    #[inline]
+    fn bitor(self, other: BlitMask) -> BlitMask {
+        BlitMask(self.0 | other.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
-#[allow(non_camel_case_types)]
-#[non_exhaustive]
-pub enum FramebufferStatus {
-    ERROR = 0,
-    FRAMEBUFFER_COMPLETE = gl::FRAMEBUFFER_COMPLETE,
-    FRAMEBUFFER_UNDEFINED = gl::FRAMEBUFFER_UNDEFINED,
-    FRAMEBUFFER_INCOMPLETE_ATTACHMENT = gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT,
-    FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT = gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT,
-    FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER = gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER,
-    FRAMEBUFFER_INCOMPLETE_READ_BUFFER = gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER,
-    FRAMEBUFFER_UNSUPPORTED = gl::FRAMEBUFFER_UNSUPPORTED,
-    FRAMEBUFFER_INCOMPLETE_MULTISAMPLE = gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE,
-    FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS = gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS,
-}
-
-pub unsafe fn CheckFramebufferStatus<FT: IsFramebufferTarget>(target: FT) -> FramebufferStatus {
-    // NOTE: The transmute can lead to undefined behaviour when a driver
-    // doesn't return a value that can be represented by the enum. It
-    // would be safer and perhaps acceptable to use a switch statement
-    // instead.
-    ::std::mem::transmute::<u32, FramebufferStatus>(gl::CheckFramebufferStatus(target.as_enum()))
+pub enum BlitFilter {
+    Nearest = gl::NEAREST,
+    Linear = gl::LINEAR,
+}
+
+/// The reason a blit was not performed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlitError {
+    /// The mask selects no buffers.
+    EmptyMask,
+    /// Depth and stencil can only be copied with nearest filtering.
+    LinearDepthStencil,
+    /// The read framebuffer is incomplete.
+    IncompleteSource(FramebufferIncomplete),
+    /// The draw framebuffer is incomplete.
+    IncompleteDestination(FramebufferIncomplete),
+    /// The read buffer and this draw buffer do not both have integer, or
+    /// both have non-integer, formats.
+    ColorFormatMismatch(Option<FramebufferAttachment>),
+    /// Integer color can only be copied with nearest filtering.
+    LinearInteger,
+    /// The depth or stencil formats of the framebuffers are not identical.
+    DepthStencilFormatMismatch,
+    /// A single sampled source can not be copied into a multisampled
+    /// destination.
+    MultisampleDestination,
+    /// Both framebuffers are multisampled with different sample counts.
+    SampleCountMismatch,
+    /// The read buffer is multisampled and the format of this draw buffer
+    /// is not identical to its format.
+    ResolveFormatMismatch(Option<FramebufferAttachment>),
+    /// A multisampled source can only be resolved into a rectangle of the
+    /// same size.
+    MultisampleScale,
+}
+
+impl ::std::fmt::Display for BlitError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            BlitError::EmptyMask => write!(f, "the blit mask is empty"),
+            BlitError::LinearDepthStencil => {
+                write!(f, "depth and stencil can not be blitted with a linear filter")
+            }
+            BlitError::IncompleteSource(reason) => {
+                write!(f, "the source framebuffer is incomplete: {}", reason)
+            }
+            BlitError::IncompleteDestination(reason) => {
+                write!(f, "the destination framebuffer is incomplete: {}", reason)
+            }
+            BlitError::ColorFormatMismatch(Some(a)) => write!(
+                f,
+                "draw buffer {:?} can not be blitted to from the read buffer format",
+                a
+            ),
+            BlitError::ColorFormatMismatch(None) => write!(
+                f,
+                "a draw buffer can not be blitted to from the read buffer format"
+            ),
+            BlitError::LinearInteger => {
+                write!(f, "integer color can not be blitted with a linear filter")
+            }
+            BlitError::DepthStencilFormatMismatch => {
+                write!(f, "the depth or stencil formats differ")
+            }
+            BlitError::MultisampleDestination => {
+                write!(f, "the destination is multisampled but the source is not")
+            }
+            BlitError::SampleCountMismatch => write!(f, "the sample counts differ"),
+            BlitError::ResolveFormatMismatch(Some(a)) => write!(
+                f,
+                "draw buffer {:?} can not be resolved into from the read buffer format",
+                a
+            ),
+            BlitError::ResolveFormatMismatch(None) => write!(
+                f,
+                "a draw buffer can not be resolved into from the read buffer format"
+            ),
+            BlitError::MultisampleScale => {
+                write!(f, "a multisampled source can not be scaled")
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for BlitError {}
+
+/// Checks the blit arguments that do not depend on the bound framebuffers.
+fn check_blit_parameters(mask: BlitMask, filter: BlitFilter) -> Result<(), BlitError> {
+    if mask.is_empty() {
+        return Err(BlitError::EmptyMask);
+    }
+    if filter == BlitFilter::Linear
+        && (mask.contains(BlitMask::DEPTH) || mask.contains(BlitMask::STENCIL))
+    {
+        return Err(BlitError::LinearDepthStencil);
+    }
+    Ok(())
+}
+
+/// The attachment parameter name for an attachment point or read buffer.
+/// The buffers of the default framebuffer are named differently.
+#[inline]
+fn buffer_attachment_enum(buffer: GLenum) -> GLenum {
+    match buffer {
+        gl::BACK => gl::BACK_LEFT,
+        gl::FRONT => gl::FRONT_LEFT,
+        _ => buffer,
+    }
+}
+
+#[inline]
+unsafe fn is_integer_component_type(target: GLenum, attachment: GLenum) -> bool {
+    let component_type = attachment_parameter(
+        target,
+        attachment,
+        gl::FRAMEBUFFER_ATTACHMENT_COMPONENT_TYPE,
+    ) as GLenum;
+    component_type == gl::INT || component_type == gl::UNSIGNED_INT
+}
+
+/// The parameters that make up the format of a color attachment.
+#[inline]
+unsafe fn color_format(target: GLenum, attachment: GLenum) -> [GLint; 6] {
+    let mut format = [0; 6];
+    for (value, &pname) in format.iter_mut().zip(&[
+        gl::FRAMEBUFFER_ATTACHMENT_RED_SIZE,
+        gl::FRAMEBUFFER_ATTACHMENT_GREEN_SIZE,
+        gl::FRAMEBUFFER_ATTACHMENT_BLUE_SIZE,
+        gl::FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE,
+        gl::FRAMEBUFFER_ATTACHMENT_COMPONENT_TYPE,
+        gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+    ]) {
+        *value = attachment_parameter(target, attachment, pname);
+    }
+    format
+}
+
+/// Returns SAMPLE_BUFFERS and SAMPLES of the draw framebuffer.
+#[inline]
+unsafe fn draw_sample_buffers_and_samples() -> (GLint, GLint) {
+    let mut sample_buffers: GLint = 0;
+    let mut samples: GLint = 0;
+    gl::GetIntegerv(gl::SAMPLE_BUFFERS, &mut sample_buffers);
+    gl::GetIntegerv(gl::SAMPLES, &mut samples);
+    (sample_buffers, samples)
+}
+
+/// Returns SAMPLE_BUFFERS and SAMPLES of the read framebuffer. Before
+/// OpenGL 4.5 these can only be queried for the draw framebuffer, in which
+/// case `None` is returned.
+#[inline]
+unsafe fn read_sample_buffers_and_samples() -> Option<(GLint, GLint)> {
+    let mut major_version: GLint = 0;
+    let mut minor_version: GLint = 0;
+    gl::GetIntegerv(gl::MAJOR_VERSION, &mut major_version);
+    gl::GetIntegerv(gl::MINOR_VERSION, &mut minor_version);
+    if (major_version, minor_version) < (4, 5) {
+        return None;
+    }
+    let name = bound_framebuffer_name(gl::READ_FRAMEBUFFER);
+    let mut sample_buffers: GLint = 0;
+    let mut samples: GLint = 0;
+    gl::GetNamedFramebufferParameteriv(name, gl::SAMPLE_BUFFERS, &mut sample_buffers);
+    gl::GetNamedFramebufferParameteriv(name, gl::SAMPLES, &mut samples);
+    Some((sample_buffers, samples))
+}
+
+/// Checks the blit against the framebuffers bound to the read and draw
+/// targets. The sample counts of the read framebuffer are only checked on
+/// OpenGL 4.5 and up.
+unsafe fn check_blit_framebuffers(
+    source_rect: Rect,
+    destination_rect: Rect,
+    mask: BlitMask,
+    filter: BlitFilter,
+) -> Result<(), BlitError> {
+    framebuffer_status(gl::READ_FRAMEBUFFER).map_err(BlitError::IncompleteSource)?;
+    framebuffer_status(gl::DRAW_FRAMEBUFFER).map_err(BlitError::IncompleteDestination)?;
+
+    let source_samples = read_sample_buffers_and_samples();
+    let source_multisampled = source_samples.map_or(false, |(sample_buffers, _)| sample_buffers > 0);
+
+    if mask.contains(BlitMask::COLOR) {
+        let mut read_buffer: GLint = 0;
+        gl::GetIntegerv(gl::READ_BUFFER, &mut read_buffer);
+
+        if read_buffer as GLenum != gl::NONE {
+            let source_attachment = buffer_attachment_enum(read_buffer as GLenum);
+            let source_integer = is_integer_component_type(gl::READ_FRAMEBUFFER, source_attachment);
+            if source_integer && filter == BlitFilter::Linear {
+                return Err(BlitError::LinearInteger);
+            }
+
+            let mut max_draw_buffers: GLint = 0;
+            gl::GetIntegerv(gl::MAX_DRAW_BUFFERS, &mut max_draw_buffers);
+            for index in 0..max_draw_buffers as GLenum {
+                let mut draw_buffer: GLint = 0;
+                gl::GetIntegerv(gl::DRAW_BUFFER0 + index, &mut draw_buffer);
+                if draw_buffer as GLenum == gl::NONE {
+                    continue;
+                }
+                let destination_attachment = buffer_attachment_enum(draw_buffer as GLenum);
+                let destination_integer =
+                    is_integer_component_type(gl::DRAW_FRAMEBUFFER, destination_attachment);
+                if source_integer != destination_integer {
+                    return Err(BlitError::ColorFormatMismatch(
+                        FramebufferAttachment::from_enum(draw_buffer as GLenum),
+                    ));
+                }
+                // Resolving does not convert between formats.
+                if source_multisampled
+                    && color_format(gl::READ_FRAMEBUFFER, source_attachment)
+                        != color_format(gl::DRAW_FRAMEBUFFER, destination_attachment)
+                {
+                    return Err(BlitError::ResolveFormatMismatch(
+                        FramebufferAttachment::from_enum(draw_buffer as GLenum),
+                    ));
+                }
+            }
+        }
+    }
+
+    for &(bit, attachment, pname) in &[
+        (BlitMask::DEPTH, gl::DEPTH, gl::FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE),
+        (BlitMask::STENCIL, gl::STENCIL, gl::FRAMEBUFFER_ATTACHMENT_STENCIL_SIZE),
+    ] {
+        if !mask.contains(bit) {
+            continue;
+        }
+        let parameters = |target: GLenum| {
+            let attachment = if bound_framebuffer_name(target) == 0 {
+                attachment
+            } else if attachment == gl::DEPTH {
+                gl::DEPTH_ATTACHMENT
+            } else {
+                gl::STENCIL_ATTACHMENT
+            };
+            if attachment_parameter(target, attachment, gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE)
+                == gl::NONE as GLint
+            {
+                return None;
+            }
+            Some((
+                attachment_parameter(target, attachment, pname),
+                attachment_parameter(target, attachment, gl::FRAMEBUFFER_ATTACHMENT_COMPONENT_TYPE),
+            ))
+        };
+        let source = parameters(gl::READ_FRAMEBUFFER);
+        let destination = parameters(gl::DRAW_FRAMEBUFFER);
+        if source.is_some() && destination.is_some() && source != destination {
+            return Err(BlitError::DepthStencilFormatMismatch);
+        }
+    }
+
+    let (source_sample_buffers, source_samples) = match source_samples {
+        Some(source_samples) => source_samples,
+        None => return Ok(()),
+    };
+    let (destination_sample_buffers, destination_samples) = draw_sample_buffers_and_samples();
+
+    match (source_sample_buffers > 0, destination_sample_buffers > 0) {
+        (false, true) => Err(BlitError::MultisampleDestination),
+        (true, true) if source_samples != destination_samples => {
+            Err(BlitError::SampleCountMismatch)
+        }
+        (true, _) if !source_rect.same_size(&destination_rect) => {
+            Err(BlitError::MultisampleScale)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[inline]
+unsafe fn bound_framebuffer_name(target: GLenum) -> GLuint {
+    let mut value: GLint = 0;
+    gl::GetIntegerv(
+        match target {
+            gl::READ_FRAMEBUFFER => gl::READ_FRAMEBUFFER_BINDING,
+            _ => gl::DRAW_FRAMEBUFFER_BINDING,
+        },
+        &mut value,
+    );
+    value as GLuint
+}
+
+#[inline]
+unsafe fn attachment_parameter(target: GLenum, attachment: GLenum, pname: GLenum) -> GLint {
+    let mut value: GLint = 0;
+    gl::GetFramebufferAttachmentParameteriv(target, attachment, pname, &mut value);
+    value
+}
+
+#[inline]
+unsafe fn has_image(target: GLenum, attachment: FramebufferAttachment) -> bool {
+    attachment_parameter(
+        target,
+        attachment as GLenum,
+        gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE,
+    ) != gl::NONE as GLint
+}
+
+#[inline]
+unsafe fn max_color_attachments() -> u32 {
+    let mut value: GLint = 0;
+    gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut value);
+    ::std::cmp::min(value as u32, COLOR_ATTACHMENTS.len() as u32)
+}
+
+/// Finds an attachment whose image can not be rendered to at its
+/// attachment point, for example a depth format on a color attachment.
+unsafe fn find_incomplete_attachment(target: GLenum) -> Option<FramebufferAttachment> {
+    let size = |attachment: FramebufferAttachment, pname: GLenum| {
+        attachment_parameter(target, attachment as GLenum, pname)
+    };
+
+    for index in 0..max_color_attachments() {
+        let attachment = COLOR_ATTACHMENTS[index as usize];
+        if has_image(target, attachment)
+            && size(attachment, gl::FRAMEBUFFER_ATTACHMENT_RED_SIZE)
+                + size(attachment, gl::FRAMEBUFFER_ATTACHMENT_GREEN_SIZE)
+                + size(attachment, gl::FRAMEBUFFER_ATTACHMENT_BLUE_SIZE)
+                + size(attachment, gl::FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE) == 0
+        {
+            return Some(attachment);
+        }
+    }
+
+    let depth = FramebufferAttachment::Depth;
+    if has_image(target, depth) && size(depth, gl::FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE) == 0 {
+        return Some(depth);
+    }
+
+    let stencil = FramebufferAttachment::Stencil;
+    if has_image(target, stencil) && size(stencil, gl::FRAMEBUFFER_ATTACHMENT_STENCIL_SIZE) == 0 {
+        return Some(stencil);
+    }
+
+    None
+}
+
+/// Finds a draw buffer that names an attachment point without an image.
+/// Only meaningful when the framebuffer is bound to the draw target.
+unsafe fn find_incomplete_draw_buffer(target: GLenum) -> Option<FramebufferAttachment> {
+    let mut max_draw_buffers: GLint = 0;
+    gl::GetIntegerv(gl::MAX_DRAW_BUFFERS, &mut max_draw_buffers);
+
+    for index in 0..max_draw_buffers as GLenum {
+        let mut value: GLint = 0;
+        gl::GetIntegerv(gl::DRAW_BUFFER0 + index, &mut value);
+        if let Some(attachment) = FramebufferAttachment::from_enum(value as GLenum) {
+            if !has_image(target, attachment) {
+                return Some(attachment);
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the read buffer if it names an attachment point without an
+/// image. Only meaningful when the framebuffer is bound to the read
+/// target.
+unsafe fn find_incomplete_read_buffer(target: GLenum) -> Option<FramebufferAttachment> {
+    let mut value: GLint = 0;
+    gl::GetIntegerv(gl::READ_BUFFER, &mut value);
+    FramebufferAttachment::from_enum(value as GLenum)
+        .and_then(|attachment| if has_image(target, attachment) {
+            None
+        } else {
+            Some(attachment)
+        })
+}
+
+/// Maps `glCheckFramebufferStatus` to a result, finding the offending
+/// attachment where possible.
+unsafe fn framebuffer_status(target: GLenum) -> Result<(), FramebufferIncomplete> {
+    let draw = target != gl::READ_FRAMEBUFFER;
+    let read = target != gl::DRAW_FRAMEBUFFER;
+    match gl::CheckFramebufferStatus(target) {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        gl::FRAMEBUFFER_UNDEFINED => Err(FramebufferIncomplete::Undefined),
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Err(
+            FramebufferIncomplete::IncompleteAttachment(find_incomplete_attachment(target)),
+        ),
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
+            Err(FramebufferIncomplete::MissingAttachment)
+        }
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => Err(
+            FramebufferIncomplete::IncompleteDrawBuffer(if draw {
+                find_incomplete_draw_buffer(target)
+            } else {
+                None
+            }),
+        ),
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => Err(
+            FramebufferIncomplete::IncompleteReadBuffer(if read {
+                find_incomplete_read_buffer(target)
+            } else {
+                None
+            }),
+        ),
+        gl::FRAMEBUFFER_UNSUPPORTED => Err(FramebufferIncomplete::Unsupported),
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => {
+            Err(FramebufferIncomplete::IncompleteMultisample)
+        }
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => {
+            Err(FramebufferIncomplete::IncompleteLayerTargets)
+        }
+        status => Err(FramebufferIncomplete::Unknown(status)),
+    }
 }
 
 /// A slot represents a resource that can be occupied.
@@ -121,8 +812,22 @@ pub unsafe fn CheckFramebufferStatus<FT: IsFramebufferTarget>(target: FT) -> Fra
 /// texture_unit_target(texture_unit_slot)
 /// bound_texture_id(texture_unit_target, texture_id)
 
-pub struct DrawFramebufferSlot {}
-pub struct ReadFramebufferSlot {}
+pub struct DrawFramebufferSlot(());
+pub struct ReadFramebufferSlot(());
+
+impl DrawFramebufferSlot {
+    #[inline]
+    pub(crate) unsafe fn new() -> Self {
+        DrawFramebufferSlot(())
+    }
+}
+
+impl ReadFramebufferSlot {
+    #[inline]
+    pub(crate) unsafe fn new() -> Self {
+        ReadFramebufferSlot(())
+    }
+}
 
 pub struct DrawReadFramebufferTarget<'a>(
     PhantomData<&'a mut DrawFramebufferSlot>,
@@ -130,7 +835,7 @@ pub struct DrawReadFramebufferTarget<'a>(
 );
 
 impl<'a> DrawReadFramebufferTarget<'a> {
-    pub fn new(draw: &'a mut DrawFramebufferSlot, read: &mut ReadFramebufferSlot) -> Self {
+    pub fn new(draw: &'a mut DrawFramebufferSlot, read: &'a mut ReadFramebufferSlot) -> Self {
         let _ = (draw, read);
         DrawReadFramebufferTarget(PhantomData, PhantomData)
     }
@@ -148,7 +853,7 @@ impl<'a> DrawFramebufferTarget<'a> {
 pub struct ReadFramebufferTarget<'a>(PhantomData<&'a mut ReadFramebufferSlot>);
 
 impl<'a> ReadFramebufferTarget<'a> {
-    pub fn new(read: &mut ReadFramebufferSlot) -> Self {
+    pub fn new(read: &'a mut ReadFramebufferSlot) -> Self {
         let _ = read;
         ReadFramebufferTarget(PhantomData)
     }
@@ -226,13 +931,61 @@ pub trait IsFramebufferTarget: Sized {
 }
 
 /// Functions available to bound framebuffers with a draw slot.
-pub trait IsDrawableBoundFramebufferId {
-    fn draw(&self) -> bool;
+pub trait IsDrawableBoundFramebufferId: Sized {
+    /// Copies a rectangle of the read buffer of `source` into the draw
+    /// buffers of this framebuffer. See `IsReadableBoundFramebufferId::blit_to`.
+    fn blit_from<TSource: IsReadableBoundFramebufferId>(
+        &mut self,
+        source: &TSource,
+        source_rect: Rect,
+        destination_rect: Rect,
+        mask: BlitMask,
+        filter: BlitFilter,
+    ) -> Result<(), BlitError>;
 }
 
 /// Functions available to bound framebuffers with a read slot.
 pub trait IsReadableBoundFramebufferId {
-    fn read(&self) -> bool;
+    /// Copies `source_rect` of the read buffer of this framebuffer into
+    /// `destination_rect` of the draw buffers of `destination`, scaling
+    /// with `filter` when the rectangles differ in size.
+    ///
+    /// The blit is checked before it is performed: both framebuffers must
+    /// be complete, depth and stencil can only be copied with nearest
+    /// filtering between identical formats, integer and floating point
+    /// color formats can not be mixed, and multisampled sources can only be
+    /// resolved into same-sized rectangles of single sampled destinations
+    /// with identical color formats. Sample counts of the source can only
+    /// be queried, and are only checked, on OpenGL 4.5 and up.
+    fn blit_to<TDestination: IsDrawableBoundFramebufferId>(
+        &self,
+        destination: &mut TDestination,
+        source_rect: Rect,
+        destination_rect: Rect,
+        mask: BlitMask,
+        filter: BlitFilter,
+    ) -> Result<(), BlitError>;
+
+    /// Like `blit_to`, but only checks the mask and filter and not the
+    /// bound framebuffers, which takes a number of queries. Meant for blits
+    /// that run every frame between framebuffers that are known to match.
+    /// OpenGL ignores an invalid blit and raises `GL_INVALID_OPERATION`.
+    fn blit_to_unchecked<TDestination: IsDrawableBoundFramebufferId>(
+        &self,
+        destination: &mut TDestination,
+        source_rect: Rect,
+        destination_rect: Rect,
+        mask: BlitMask,
+        filter: BlitFilter,
+    ) -> Result<(), BlitError>;
+
+    /// Reads a rectangle of the read buffer into an image with the first
+    /// row at the top. The pixel type determines the format and type passed
+    /// to `glReadPixels`.
+    fn read_pixels<P: TexturePixel>(
+        &self,
+        rect: Rect,
+    ) -> Result<image::ImageBuffer<P, Vec<P::Subpixel>>, String>;
 }
 
 #[must_use]
@@ -251,8 +1004,16 @@ where
     TMaybeDefaultFramebufferId: 'a + MaybeDefaultFramebufferId,
     THasDrawFramebufferSlot: 'a + HasDrawFramebufferSlot<'a>,
 {
-    fn draw(&self) -> bool {
-        false
+    #[inline]
+    fn blit_from<TSource: IsReadableBoundFramebufferId>(
+        &mut self,
+        source: &TSource,
+        source_rect: Rect,
+        destination_rect: Rect,
+        mask: BlitMask,
+        filter: BlitFilter,
+    ) -> Result<(), BlitError> {
+        source.blit_to(self, source_rect, destination_rect, mask, filter)
     }
 }
 
@@ -262,69 +1023,353 @@ where
     TMaybeDefaultFramebufferId: 'a + MaybeDefaultFramebufferId,
     THasReadFramebufferSlot: 'a + HasReadFramebufferSlot<'a>,
 {
-    fn read(&self) -> bool {
-        false
+    fn blit_to<TDestination: IsDrawableBoundFramebufferId>(
+        &self,
+        destination: &mut TDestination,
+        source_rect: Rect,
+        destination_rect: Rect,
+        mask: BlitMask,
+        filter: BlitFilter,
+    ) -> Result<(), BlitError> {
+        check_blit_parameters(mask, filter)?;
+        unsafe {
+            check_blit_framebuffers(source_rect, destination_rect, mask, filter)?;
+        }
+        self.blit_to_unchecked(destination, source_rect, destination_rect, mask, filter)
+    }
+
+    fn blit_to_unchecked<TDestination: IsDrawableBoundFramebufferId>(
+        &self,
+        _destination: &mut TDestination,
+        source_rect: Rect,
+        destination_rect: Rect,
+        mask: BlitMask,
+        filter: BlitFilter,
+    ) -> Result<(), BlitError> {
+        check_blit_parameters(mask, filter)?;
+        unsafe {
+            gl::BlitFramebuffer(
+                source_rect.x,
+                source_rect.y,
+                source_rect.x + source_rect.width,
+                source_rect.y + source_rect.height,
+                destination_rect.x,
+                destination_rect.y,
+                destination_rect.x + destination_rect.width,
+                destination_rect.y + destination_rect.height,
+                mask.0,
+                filter as GLenum,
+            );
+        }
+        Ok(())
+    }
+
+    fn read_pixels<P: TexturePixel>(
+        &self,
+        rect: Rect,
+    ) -> Result<image::ImageBuffer<P, Vec<P::Subpixel>>, String> {
+        if rect.width <= 0 || rect.height <= 0 {
+            return Err(format!("Can not read pixels from the empty {:?}.", rect));
+        }
+
+        let mut pixels = image::ImageBuffer::<P, Vec<P::Subpixel>>::new(
+            rect.width as u32,
+            rect.height as u32,
+        );
+        unsafe {
+            with_tight_rows(gl::PACK_ALIGNMENT, || {
+                gl::ReadPixels(
+                    rect.x,
+                    rect.y,
+                    rect.width,
+                    rect.height,
+                    P::FORMAT,
+                    P::COMPONENT_FORMAT,
+                    pixels.as_mut_ptr() as *mut GLvoid,
+                );
+            });
+        }
+
+        // OpenGL returns the bottom row first.
+        Ok(image::imageops::flip_vertical(&pixels))
+    }
+}
+
+impl<'a, TMaybeDefaultFramebufferId, TFramebufferTarget>
+    BoundFramebufferId<'a, TMaybeDefaultFramebufferId, TFramebufferTarget>
+where
+    TMaybeDefaultFramebufferId: 'a + MaybeDefaultFramebufferId,
+    TFramebufferTarget: 'a + IsFramebufferTarget,
+{
+    /// Checks whether the bound framebuffer can be rendered to or read
+    /// from.
+    pub fn check_status(&self) -> Result<(), FramebufferIncomplete> {
+        unsafe { framebuffer_status(self.target.as_enum()) }
+    }
+}
+
+impl<'a, TMaybeDefaultFramebufferId, THasDrawFramebufferSlot>
+    BoundFramebufferId<'a, TMaybeDefaultFramebufferId, THasDrawFramebufferSlot>
+where
+    TMaybeDefaultFramebufferId: 'a + MaybeDefaultFramebufferId,
+    THasDrawFramebufferSlot: 'a + HasDrawFramebufferSlot<'a>,
+{
+    /// Clears a color draw buffer of a floating point or normalized
+    /// format. `draw_buffer` is an index into the draw buffers, not a color
+    /// attachment index.
+    #[inline]
+    pub fn clear_color_f(&mut self, draw_buffer: u32, value: [GLfloat; 4]) -> &mut Self {
+        unsafe {
+            gl::ClearBufferfv(gl::COLOR, draw_buffer as GLint, value.as_ptr());
+        }
+        self
+    }
+
+    /// Clears a color draw buffer of a signed integer format.
+    #[inline]
+    pub fn clear_color_i(&mut self, draw_buffer: u32, value: [GLint; 4]) -> &mut Self {
+        unsafe {
+            gl::ClearBufferiv(gl::COLOR, draw_buffer as GLint, value.as_ptr());
+        }
+        self
+    }
+
+    /// Clears a color draw buffer of an unsigned integer format.
+    #[inline]
+    pub fn clear_color_ui(&mut self, draw_buffer: u32, value: [GLuint; 4]) -> &mut Self {
+        unsafe {
+            gl::ClearBufferuiv(gl::COLOR, draw_buffer as GLint, value.as_ptr());
+        }
+        self
+    }
+
+    #[inline]
+    pub fn clear_depth(&mut self, depth: GLfloat) -> &mut Self {
+        unsafe {
+            gl::ClearBufferfv(gl::DEPTH, 0, &depth);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn clear_stencil(&mut self, stencil: GLint) -> &mut Self {
+        unsafe {
+            gl::ClearBufferiv(gl::STENCIL, 0, &stencil);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn clear_depth_stencil(&mut self, depth: GLfloat, stencil: GLint) -> &mut Self {
+        unsafe {
+            gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil);
+        }
+        self
+    }
+}
+
+impl<'a, THasDrawFramebufferSlot> BoundFramebufferId<'a, FramebufferId, THasDrawFramebufferSlot>
+where
+    THasDrawFramebufferSlot: 'a + HasDrawFramebufferSlot<'a>,
+{
+    /// Selects the color attachments that fragment shader outputs are
+    /// written to. Output location `i` is written to `buffers[i]`, `None`
+    /// discards it.
+    pub fn draw_buffers(
+        &mut self,
+        buffers: &[Option<FramebufferAttachment>],
+    ) -> Result<&mut Self, String> {
+        let mut max_draw_buffers: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_DRAW_BUFFERS, &mut max_draw_buffers);
+        }
+        let enums = draw_buffer_enums(buffers, max_draw_buffers as usize)?;
+        unsafe {
+            gl::DrawBuffers(enums.len() as GLsizei, enums.as_ptr());
+        }
+        Ok(self)
+    }
+}
+
+impl<'a, THasReadFramebufferSlot> BoundFramebufferId<'a, FramebufferId, THasReadFramebufferSlot>
+where
+    THasReadFramebufferSlot: 'a + HasReadFramebufferSlot<'a>,
+{
+    /// Selects the color attachment that is read from by `read_pixels` and
+    /// blits. `None` disables reading color.
+    pub fn read_buffer(
+        &mut self,
+        attachment: Option<FramebufferAttachment>,
+    ) -> Result<&mut Self, String> {
+        let value = match attachment {
+            Some(attachment) => {
+                if attachment.color_index().is_none() {
+                    return Err(format!("{:?} is not a color attachment.", attachment));
+                }
+                attachment as GLenum
+            }
+            None => gl::NONE,
+        };
+        unsafe {
+            gl::ReadBuffer(value);
+        }
+        Ok(self)
+    }
+}
+
+/// Validates draw buffers against the rules of `glDrawBuffers` that can be
+/// checked without a context, apart from the `max_draw_buffers` limit.
+fn draw_buffer_enums(
+    buffers: &[Option<FramebufferAttachment>],
+    max_draw_buffers: usize,
+) -> Result<Vec<GLenum>, String> {
+    if buffers.len() > max_draw_buffers {
+        return Err(format!(
+            "{} draw buffers exceed GL_MAX_DRAW_BUFFERS ({}).",
+            buffers.len(),
+            max_draw_buffers
+        ));
+    }
+
+    for (index, buffer) in buffers.iter().enumerate() {
+        if let Some(attachment) = *buffer {
+            if attachment.color_index().is_none() {
+                return Err(format!(
+                    "Draw buffer {} is {:?}, which is not a color attachment.",
+                    index, attachment
+                ));
+            }
+            if buffers[..index].contains(buffer) {
+                return Err(format!(
+                    "{:?} is selected by more than one draw buffer.",
+                    attachment
+                ));
+            }
+        }
     }
+
+    Ok(buffers
+        .iter()
+        .map(|buffer| buffer.map_or(gl::NONE, |attachment| attachment as GLenum))
+        .collect())
 }
 
 impl<'a> BoundFramebufferId<'a, FramebufferId, DrawReadFramebufferTarget<'a>> {
-    /// glFramebufferTexture2D attaches the texture image specified by
-    /// texture and level as one of the logical buffers of the currently
-    /// bound framebuffer object. attachment specifies whether the texture
-    /// image should be attached to the framebuffer object's color, depth,
-    /// or stencil buffer. A texture image may not be attached to the
-    /// default framebuffer object name 0.
-    ///
-    /// If texture is not 0, the value of
-    /// GL_FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE for the specified attachment
-    /// point is set to GL_TEXTURE, the value of
-    /// GL_FRAMEBUFFER_ATTACHMENT_OBJECT_NAME is set to texture, and the
-    /// value of GL_FRAMEBUFFER_ATTACHMENT_TEXTURE_LEVEL is set to level. If
-    /// texture is a cube map texture, the value of
-    /// GL_FRAMEBUFFER_ATTACHMENT_TEXTURE_CUBE_MAP_FACE is set to textarget;
-    /// otherwise it is set to the default value
-    /// GL_TEXTURE_CUBE_MAP_POSITIVE_X. Any previous attachment to the
-    /// attachment logical buffer of the currently bound framebuffer object
-    /// is broken.
-    ///
-    /// If texture is 0, the current image, if any, attached to the
-    /// attachment logical buffer of the currently bound framebuffer object
-    /// is detached. The value of GL_FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE is
-    /// set to GL_NONE. The value of GL_FRAMEBUFFER_ATTACHMENT_OBJECT_NAME
-    /// is set to 0. GL_FRAMEBUFFER_ATTACHMENT_TEXTURE_LEVEL and
-    /// GL_FRAMEBUFFER_ATTACHMENT_TEXTURE_CUBE_MAP_FACE are set to the
-    /// default values 0 and GL_TEXTURE_CUBE_MAP_POSITIVE_X, respectively.
-    pub unsafe fn attach_texture_2d(
+    /// Attaches a mipmap level of a 2D, rectangle or multisampled texture.
+    pub fn attach_texture_2d<'t, 'i, TTextureTarget>(
         &mut self,
         attachment: FramebufferAttachment,
-        textarget: GLenum,
-        texture_id: &TextureId,
+        texture: &BoundTextureId<'t, 'i, TTextureTarget>,
         level: GLint,
-    ) -> &mut Self {
-        gl::FramebufferTexture2D(
-            self.target.as_enum(),
-            attachment as GLenum,
-            textarget,
-            texture_id.as_u32(),
-            level,
-        );
-        self
+    ) -> Result<&mut Self, AttachmentError>
+    where
+        TTextureTarget: 't + TextureTargetFramebuffer2D,
+    {
+        check_texture(texture, level, None)?;
+        unsafe {
+            gl::FramebufferTexture2D(
+                self.target.as_enum(),
+                attachment as GLenum,
+                texture.target_as_enum(),
+                texture.texture_id().as_u32(),
+                level,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Attaches a mipmap level of a single face of a cube map.
+    pub fn attach_cube_map_face<'s, 't, 'i>(
+        &mut self,
+        attachment: FramebufferAttachment,
+        texture: &BoundTextureId<'t, 'i, TextureTargetCubeMap<'s>>,
+        face: CubeMapFace,
+        level: GLint,
+    ) -> Result<&mut Self, AttachmentError> {
+        check_texture(texture, level, None)?;
+        unsafe {
+            gl::FramebufferTexture2D(
+                self.target.as_enum(),
+                attachment as GLenum,
+                face as GLenum,
+                texture.texture_id().as_u32(),
+                level,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Attaches a mipmap level of a single layer of a 3D or array texture.
+    pub fn attach_texture_layer<'t, 'i, TTextureTarget>(
+        &mut self,
+        attachment: FramebufferAttachment,
+        texture: &BoundTextureId<'t, 'i, TTextureTarget>,
+        level: GLint,
+        layer: GLint,
+    ) -> Result<&mut Self, AttachmentError>
+    where
+        TTextureTarget: 't + TextureTargetFramebufferLayer,
+    {
+        check_texture(texture, level, Some(layer))?;
+        unsafe {
+            gl::FramebufferTextureLayer(
+                self.target.as_enum(),
+                attachment as GLenum,
+                texture.texture_id().as_u32(),
+                level,
+                layer,
+            );
+        }
+        Ok(self)
     }
 
+    /// Attaches a mipmap level of all layers of a 3D, array or cube map
+    /// texture. A geometry shader selects the layer to render to with
+    /// `gl_Layer`.
+    pub fn attach_texture_layered<'t, 'i, TTextureTarget>(
+        &mut self,
+        attachment: FramebufferAttachment,
+        texture: &BoundTextureId<'t, 'i, TTextureTarget>,
+        level: GLint,
+    ) -> Result<&mut Self, AttachmentError>
+    where
+        TTextureTarget: 't + TextureTargetFramebufferLayered,
+    {
+        check_texture(texture, level, None)?;
+        unsafe {
+            gl::FramebufferTexture(
+                self.target.as_enum(),
+                attachment as GLenum,
+                texture.texture_id().as_u32(),
+                level,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Attaches a renderbuffer. Renderbuffers with allocated storage are
+    /// checked to have a format that can be attached at `attachment`.
     pub fn attach_renderbuffer(
         &mut self,
         attachment: FramebufferAttachment,
-        renderbuffer_id: &RenderbufferId,
-    ) -> &mut Self {
+        renderbuffer: &BoundRenderbufferId,
+    ) -> Result<&mut Self, AttachmentError> {
+        if renderbuffer.width() > 0 {
+            if let Some(format) = renderbuffer.internal_format() {
+                if !attachment.accepts(format.kind()) {
+                    return Err(AttachmentError::IncompatibleFormat(format));
+                }
+            }
+        }
         unsafe {
             gl::FramebufferRenderbuffer(
                 self.target.as_enum(),
                 attachment as GLenum,
                 gl::RENDERBUFFER,
-                renderbuffer_id.as_u32(),
+                renderbuffer.renderbuffer_id().as_u32(),
             );
         }
-        self
+        Ok(self)
     }
 
     pub fn detach_renderbuffer(
@@ -356,6 +1401,15 @@ mod tests {
     use super::BoundFramebufferId;
     use super::IsDrawableBoundFramebufferId;
     use super::IsReadableBoundFramebufferId;
+    use super::FramebufferAttachment;
+    use super::draw_buffer_enums;
+    use super::Rect;
+    use super::BlitMask;
+    use super::BlitFilter;
+    use super::BlitError;
+    use super::check_blit_parameters;
+    use super::AttachmentError;
+    use super::check_level_and_layer;
 
     #[test]
     fn default_framebuffer_id_has_size_0() {
@@ -379,37 +1433,119 @@ mod tests {
         assert_eq!(0, ::std::mem::size_of::<BoundFramebufferId<FramebufferId, DrawReadFramebufferTarget>>());
     }
 
+    #[test]
+    fn color_attachments_are_checked() {
+        assert_eq!(Some(FramebufferAttachment::Color0), FramebufferAttachment::color(0));
+        assert_eq!(Some(FramebufferAttachment::Color31), FramebufferAttachment::color(31));
+        assert_eq!(None, FramebufferAttachment::color(32));
+        assert_eq!(None, FramebufferAttachment::color(::std::u32::MAX));
+    }
+
+    #[test]
+    fn attachments_round_trip_through_enums() {
+        for &attachment in &[
+            FramebufferAttachment::Color0,
+            FramebufferAttachment::Color7,
+            FramebufferAttachment::Depth,
+            FramebufferAttachment::Stencil,
+            FramebufferAttachment::DepthStencil,
+        ] {
+            assert_eq!(Some(attachment), FramebufferAttachment::from_enum(attachment as u32));
+        }
+        assert_eq!(None, FramebufferAttachment::from_enum(0));
+        assert_eq!(Some(7), FramebufferAttachment::Color7.color_index());
+        assert_eq!(None, FramebufferAttachment::Depth.color_index());
+    }
+
+    #[test]
+    fn attachments_accept_matching_formats() {
+        use renderbuffer::RenderbufferFormatKind::*;
+        assert!(FramebufferAttachment::Color3.accepts(Color));
+        assert!(!FramebufferAttachment::Color0.accepts(Depth));
+        assert!(!FramebufferAttachment::Depth.accepts(Color));
+        assert!(FramebufferAttachment::Depth.accepts(DepthStencil));
+        assert!(FramebufferAttachment::Stencil.accepts(Stencil));
+        assert!(!FramebufferAttachment::DepthStencil.accepts(Depth));
+        assert!(FramebufferAttachment::DepthStencil.accepts(DepthStencil));
+    }
+
+    #[test]
+    fn draw_buffers_are_validated() {
+        use self::FramebufferAttachment::*;
+        assert_eq!(
+            Ok(vec![Color0 as u32, 0, Color2 as u32]),
+            draw_buffer_enums(&[Some(Color0), None, Some(Color2)], 8)
+        );
+        assert_eq!(Ok(vec![0, 0]), draw_buffer_enums(&[None, None], 8));
+        assert!(draw_buffer_enums(&[Some(Color0), Some(Color1)], 1).is_err());
+        assert!(draw_buffer_enums(&[Some(Depth)], 8).is_err());
+        assert!(draw_buffer_enums(&[Some(Color1), Some(Color1)], 8).is_err());
+    }
+
+    #[test]
+    fn blit_parameters_are_checked() {
+        assert_eq!(Ok(()), check_blit_parameters(BlitMask::COLOR, BlitFilter::Linear));
+        assert_eq!(
+            Ok(()),
+            check_blit_parameters(BlitMask::DEPTH | BlitMask::STENCIL, BlitFilter::Nearest)
+        );
+        assert_eq!(
+            Err(BlitError::LinearDepthStencil),
+            check_blit_parameters(BlitMask::COLOR | BlitMask::DEPTH, BlitFilter::Linear)
+        );
+        assert_eq!(
+            Err(BlitError::EmptyMask),
+            check_blit_parameters(BlitMask(0), BlitFilter::Nearest)
+        );
+    }
+
+    #[test]
+    fn attachment_levels_and_layers_are_checked() {
+        assert_eq!(Ok(()), check_level_and_layer(0, (4, 4, 1), None));
+        assert_eq!(Ok(()), check_level_and_layer(2, (1, 1, 6), Some(5)));
+        assert_eq!(
+            Err(AttachmentError::MissingLevel(3)),
+            check_level_and_layer(3, (0, 0, 0), None)
+        );
+        assert_eq!(
+            Err(AttachmentError::LayerOutOfRange { layer: 6, layers: 6 }),
+            check_level_and_layer(0, (8, 8, 6), Some(6))
+        );
+        assert_eq!(
+            Err(AttachmentError::LayerOutOfRange { layer: -1, layers: 6 }),
+            check_level_and_layer(0, (8, 8, 6), Some(-1))
+        );
+    }
+
     #[test]
     fn test() {
         let fb0 = DEFAULT_FRAMEBUFFER_ID;
         let fb1 = FramebufferId::new().unwrap();
         let fb2 = FramebufferId::new().unwrap();
 
-        let mut draw = DrawFramebufferSlot {};
-        let mut read = ReadFramebufferSlot {};
+        let mut draw = unsafe { DrawFramebufferSlot::new() };
+        let mut read = unsafe { ReadFramebufferSlot::new() };
+
+        let rect = Rect::from_size(1, 1);
 
         {
-            let bb0 = DrawReadFramebufferTarget::new(&mut draw, &mut read).bind(&fb0);
-            assert_eq!(bb0.draw(), false);
-            assert_eq!(bb0.read(), false);
+            let _bb0 = DrawReadFramebufferTarget::new(&mut draw, &mut read).bind(&fb0);
+            // Can not bind another framebuffer to blit to or from.
         }
 
         {
             let br0 = ReadFramebufferTarget::new(&mut read).bind(&fb0);
 
-            // assert_eq!(br0.draw(), false);
-            assert_eq!(br0.read(), false);
-
             {
-                let bd1 = DrawFramebufferTarget::new(&mut draw).bind(&fb1);
-                assert_eq!(bd1.draw(), false);
-                // assert_eq!(bd1.read(), false);
+                let mut bd1 = DrawFramebufferTarget::new(&mut draw).bind(&fb1);
+                let _ = br0.blit_to(&mut bd1, rect, rect, BlitMask::COLOR, BlitFilter::Nearest);
+                // bd1.blit_to(&mut br0, ...) does not compile.
             }
 
             {
-                let bd2 = DrawFramebufferTarget::new(&mut draw).bind(&fb2);
-                assert_eq!(bd2.draw(), false);
-                // assert_eq!(bd2.read(), false);
+                let mut bd2 = DrawFramebufferTarget::new(&mut draw).bind(&fb2);
+                let _ = bd2.blit_from(&br0, rect, rect, BlitMask::COLOR, BlitFilter::Nearest);
+                // br0.blit_from(&bd2, ...) does not compile.
             }
         }
     }
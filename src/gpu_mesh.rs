@@ -0,0 +1,209 @@
+extern crate gl;
+
+use buffer::{Buffer, BufferUsage};
+use context::Capabilities;
+use draw::DrawMode;
+use import::{Mesh, VertexData};
+use index_buffer::IndexBuffer;
+use program::BoundProgramId;
+use std::ops::Range;
+use vertex::Vertex;
+use vertex_array::{VertexArray, VertexFormat};
+
+/// The vertex buffer binding the mesh vertices are read from.
+const VERTEX_BINDING: u32 = 0;
+/// The vertex buffer binding the instance attributes are read from.
+const INSTANCE_BINDING: u32 = 1;
+
+/// Checks that every submesh covers a whole number of triangles within
+/// `index_count` indices.
+fn check_submeshes(submeshes: &[Range<usize>], index_count: usize) -> Result<(), String> {
+    for (position, submesh) in submeshes.iter().enumerate() {
+        if submesh.start > submesh.end || submesh.end > index_count {
+            return Err(format!(
+                "Submesh {} covers indices {}..{} but the mesh has {} indices.",
+                position, submesh.start, submesh.end, index_count
+            ));
+        }
+        if submesh.start % 3 != 0 || submesh.end % 3 != 0 {
+            return Err(format!(
+                "Submesh {} covers indices {}..{} which do not align with triangles.",
+                position, submesh.start, submesh.end
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A triangle mesh on the GPU. Owns the vertex array, the vertex buffer and
+/// the index buffer. Submeshes are ranges of indices that can be drawn
+/// separately, for example with different materials.
+///
+/// The vertices are read from binding 0 of the vertex format and instance
+/// attributes, when configured, from binding 1.
+#[derive(Debug)]
+pub struct GpuMesh<V: Vertex> {
+    vertex_array: VertexArray,
+    vertex_buffer: Buffer<V>,
+    index_buffer: IndexBuffer,
+    submeshes: Vec<Range<usize>>,
+    instanced: bool,
+}
+
+impl<V: Vertex + 'static> GpuMesh<V> {
+    /// Uploads the triangles `indices` into `vertices`. Without `submeshes`
+    /// the whole mesh forms a single submesh.
+    pub fn new(
+        capabilities: &Capabilities,
+        vertices: &[V],
+        indices: &[u32],
+        submeshes: &[Range<usize>],
+    ) -> Result<Self, String> {
+        if indices.len() % 3 != 0 {
+            return Err(format!(
+                "The index count {} is not a multiple of 3.",
+                indices.len()
+            ));
+        }
+
+        let submeshes = if submeshes.is_empty() {
+            vec![0..indices.len()]
+        } else {
+            check_submeshes(submeshes, indices.len())?;
+            submeshes.to_vec()
+        };
+
+        let mut format = VertexFormat::new(capabilities);
+        format.binding::<V>(VERTEX_BINDING)?;
+
+        let mut vertex_array = VertexArray::new()?;
+        let mut vertex_buffer = Buffer::new()?;
+        vertex_buffer.data(vertices, BufferUsage::StaticDraw);
+        let index_buffer = IndexBuffer::new(indices, vertices.len(), BufferUsage::StaticDraw)?;
+
+        vertex_array.set_format(format);
+        vertex_array.bind_vertex_buffer(VERTEX_BINDING, &vertex_buffer, 0)?;
+        index_buffer.bind();
+
+        Ok(GpuMesh {
+            vertex_array,
+            vertex_buffer,
+            index_buffer,
+            submeshes,
+            instanced: false,
+        })
+    }
+
+    #[inline]
+    pub fn vertex_buffer(&self) -> &Buffer<V> {
+        &self.vertex_buffer
+    }
+
+    #[inline]
+    pub fn index_buffer(&self) -> &IndexBuffer {
+        &self.index_buffer
+    }
+
+    #[inline]
+    pub fn submeshes(&self) -> &[Range<usize>] {
+        &self.submeshes
+    }
+
+    /// Reads per-instance attributes of `I` from `buffer`. The locations of
+    /// `I` may not overlap those of the mesh vertices. Afterwards the mesh
+    /// can only be drawn with `draw_instanced`. Calling this again swaps the
+    /// instance buffer, which must then hold the same instance type.
+    pub fn configure_instances<I: Vertex + 'static>(
+        &mut self,
+        buffer: &Buffer<I>,
+    ) -> Result<(), String> {
+        if !self.instanced {
+            let mut format = self.vertex_array
+                .format()
+                .cloned()
+                .expect("The vertex format is set on creation.");
+            format.binding::<I>(INSTANCE_BINDING)?;
+            // The vertex buffer stays attached to its binding.
+            self.vertex_array.set_format(format);
+            self.instanced = true;
+        }
+        self.vertex_array.bind_vertex_buffer(INSTANCE_BINDING, buffer, 0)
+    }
+
+    /// Draws every submesh with the bound program.
+    pub fn draw(&self, program: &BoundProgramId) -> Result<(), String> {
+        for index in 0..self.submeshes.len() {
+            self.draw_submesh(program, index)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the submesh at `index` with the bound program.
+    pub fn draw_submesh(&self, program: &BoundProgramId, index: usize) -> Result<(), String> {
+        if self.instanced {
+            return Err(String::from(
+                "Meshes with instance attributes must be drawn with draw_instanced.",
+            ));
+        }
+        // The vertices are only read through the indices, which were
+        // checked on creation.
+        unsafe { self.draw_submesh_instanced(program, index, 1) }
+    }
+
+    /// Draws `instance_count` instances of every submesh with the bound
+    /// program.
+    ///
+    /// Unsafe because the instance buffers added with
+    /// `configure_instances` must hold at least `instance_count` elements
+    /// for every attribute with a divisor of 1.
+    pub unsafe fn draw_instanced(
+        &self,
+        program: &BoundProgramId,
+        instance_count: usize,
+    ) -> Result<(), String> {
+        for index in 0..self.submeshes.len() {
+            self.draw_submesh_instanced(program, index, instance_count)?;
+        }
+        Ok(())
+    }
+
+    /// Draws `instance_count` instances of the submesh at `index`. See
+    /// `draw_instanced` for the requirements on instance buffers.
+    pub unsafe fn draw_submesh_instanced(
+        &self,
+        _program: &BoundProgramId,
+        index: usize,
+        instance_count: usize,
+    ) -> Result<(), String> {
+        let submesh = self.submeshes.get(index).cloned().ok_or_else(|| {
+            format!(
+                "Submesh {} is out of range for a mesh with {} submeshes.",
+                index,
+                self.submeshes.len()
+            )
+        })?;
+        self.vertex_array.id().bind();
+        self.index_buffer
+            .draw_instanced(DrawMode::Triangles, submesh, instance_count)
+    }
+}
+
+impl GpuMesh<VertexData> {
+    /// Uploads an imported mesh as a single submesh.
+    pub fn from_mesh(capabilities: &Capabilities, mesh: &Mesh) -> Result<Self, String> {
+        GpuMesh::new(capabilities, &mesh.elements, &mesh.flat_indices(), &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_submeshes;
+
+    #[test]
+    fn submeshes_cover_whole_triangles() {
+        assert!(check_submeshes(&[0..36, 36..72], 72).is_ok());
+        assert!(check_submeshes(&[0..36, 36..75], 72).is_err());
+        assert!(check_submeshes(&[0..35], 72).is_err());
+        assert!(check_submeshes(&[36..0], 72).is_err());
+    }
+}
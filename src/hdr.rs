@@ -0,0 +1,497 @@
+extern crate gl;
+extern crate image;
+
+use gl::types::*;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use texture::{BoundTextureId, TextureTargetGroup2D};
+
+pub type RgbF32Image = image::ImageBuffer<image::Rgb<f32>, Vec<f32>>;
+pub type RgbaF32Image = image::ImageBuffer<image::Rgba<f32>, Vec<f32>>;
+
+/// A floating point image. Radiance files have no alpha channel, OpenEXR
+/// files are always read as RGBA. See `load_openexr` for the OpenEXR files
+/// that can be read.
+pub enum HdrImage {
+    Rgb(RgbF32Image),
+    Rgba(RgbaF32Image),
+}
+
+impl HdrImage {
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        match *self {
+            HdrImage::Rgb(ref image) => image.dimensions(),
+            HdrImage::Rgba(ref image) => image.dimensions(),
+        }
+    }
+
+    /// Mirrors the image so that the first row becomes the bottom row of
+    /// the texture, matching the texture coordinates of imported meshes.
+    pub fn flipv(&self) -> Self {
+        match *self {
+            HdrImage::Rgb(ref image) => HdrImage::Rgb(image::imageops::flip_vertical(image)),
+            HdrImage::Rgba(ref image) => HdrImage::Rgba(image::imageops::flip_vertical(image)),
+        }
+    }
+
+    /// Uploads RGB images as `GL_RGB16F` and RGBA images as `GL_RGBA32F`.
+    pub fn upload<'t, 'i, TTextureTarget: 't + TextureTargetGroup2D>(
+        &self,
+        texture: &mut BoundTextureId<'t, 'i, TTextureTarget>,
+        mipmap_level: GLint,
+    ) {
+        match *self {
+            HdrImage::Rgb(ref image) => {
+                texture.image_2d_from(mipmap_level, gl::RGB16F as GLint, image);
+            }
+            HdrImage::Rgba(ref image) => {
+                texture.image_2d_from(mipmap_level, gl::RGBA32F as GLint, image);
+            }
+        }
+    }
+}
+
+/// Loads a Radiance `.hdr` file without clamping or converting to 8 bits.
+pub fn load_radiance<P: AsRef<Path>>(path: P) -> Result<RgbF32Image, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    decode_radiance(io::BufReader::new(file))
+}
+
+fn decode_radiance<R: io::BufRead>(reader: R) -> Result<RgbF32Image, String> {
+    let decoder = image::hdr::HDRDecoder::new(reader).map_err(|e| e.to_string())?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr().map_err(|e| e.to_string())?;
+
+    let data: Vec<f32> = pixels
+        .iter()
+        .flat_map(|p| p.data.iter().cloned())
+        .collect();
+
+    RgbF32Image::from_raw(metadata.width, metadata.height, data)
+        .ok_or_else(|| String::from("Radiance image data does not match its dimensions."))
+}
+
+/// Loads the R, G, B and A channels of a single part, scan line OpenEXR
+/// file. Missing color channels are read as 0 and a missing alpha channel as
+/// 1.
+///
+/// Only uncompressed and RLE compressed files are supported. Files written
+/// with ZIP, ZIPS, PIZ, PXR24, B44 or DWA compression, tiled files, deep
+/// files, multi-part files and subsampled channels are rejected with an
+/// error; convert them to uncompressed or RLE scan line files first.
+pub fn load_openexr<P: AsRef<Path>>(path: P) -> Result<RgbaF32Image, String> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    decode_openexr(&bytes)
+}
+
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const EXR_TILED: u32 = 0x200;
+const EXR_NON_IMAGE: u32 = 0x800;
+const EXR_MULTIPART: u32 = 0x1000;
+const EXR_NO_COMPRESSION: u8 = 0;
+const EXR_RLE_COMPRESSION: u8 = 1;
+/// Larger than any texture an OpenGL implementation accepts.
+const EXR_MAX_DIMENSION: i64 = 1 << 16;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ExrPixelType {
+    Uint,
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    fn size(&self) -> usize {
+        match *self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Uint | ExrPixelType::Float => 4,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ExrChannel {
+    name: String,
+    pixel_type: ExrPixelType,
+}
+
+/// Reads little endian values from a byte slice.
+struct ExrReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ExrReader<'a> {
+    fn new(bytes: &'a [u8], position: usize) -> Self {
+        ExrReader { bytes, position }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| String::from("Unexpected end of OpenEXR data."))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take(4)
+            .map(|b| b.iter().rev().fold(0, |value, &byte| value << 8 | byte as u32))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        self.u32().map(|value| value as i32)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.take(8)
+            .map(|b| b.iter().rev().fold(0, |value, &byte| value << 8 | byte as u64))
+    }
+
+    /// Reads a null terminated string.
+    fn string(&mut self) -> Result<String, String> {
+        let rest = &self.bytes[self.position..];
+        let length = rest.iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| String::from("Unterminated string in OpenEXR header."))?;
+        let string = String::from_utf8_lossy(&rest[..length]).into_owned();
+        self.position += length + 1;
+        Ok(string)
+    }
+}
+
+fn parse_exr_channels(value: &[u8]) -> Result<Vec<ExrChannel>, String> {
+    let mut reader = ExrReader::new(value, 0);
+    let mut channels = Vec::new();
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            return Ok(channels);
+        }
+        let pixel_type = match reader.i32()? {
+            0 => ExrPixelType::Uint,
+            1 => ExrPixelType::Half,
+            2 => ExrPixelType::Float,
+            other => return Err(format!("Unknown OpenEXR pixel type {}.", other)),
+        };
+        // Skip pLinear and the reserved bytes.
+        reader.take(4)?;
+        let (x_sampling, y_sampling) = (reader.i32()?, reader.i32()?);
+        if (x_sampling, y_sampling) != (1, 1) {
+            return Err(format!("OpenEXR channel {} is subsampled.", name));
+        }
+        channels.push(ExrChannel { name, pixel_type });
+    }
+}
+
+/// Converts an IEEE 754 half precision float to single precision.
+fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // Subnormal halfs are normal floats.
+            let mut exponent = 127 - 15 + 1;
+            let mut mantissa = mantissa;
+            while mantissa & 0x400 == 0 {
+                mantissa <<= 1;
+                exponent -= 1;
+            }
+            sign | exponent << 23 | (mantissa & 0x3ff) << 13
+        }
+        0x1f => sign | 0x7f80_0000 | mantissa << 13,
+        _ => sign | (exponent + 127 - 15) << 23 | mantissa << 13,
+    };
+    f32::from_bits(bits)
+}
+
+/// Undoes the run length encoding, byte delta prediction and byte
+/// reordering that OpenEXR applies to RLE compressed scan lines.
+fn decompress_exr_rle(data: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(size);
+    let mut reader = ExrReader::new(data, 0);
+    while reader.position < data.len() {
+        let count = reader.u8()? as i8;
+        if count < 0 {
+            decoded.extend_from_slice(reader.take(-(count as isize) as usize)?);
+        } else {
+            let value = reader.u8()?;
+            decoded.extend((0..count as usize + 1).map(|_| value));
+        }
+    }
+    if decoded.len() != size {
+        return Err(format!(
+            "RLE data decompressed to {} bytes instead of {}.",
+            decoded.len(),
+            size
+        ));
+    }
+
+    for index in 1..decoded.len() {
+        decoded[index] = decoded[index - 1]
+            .wrapping_add(decoded[index])
+            .wrapping_sub(128);
+    }
+
+    let (first, second) = decoded.split_at((size + 1) / 2);
+    Ok((0..size)
+        .map(|index| {
+            if index % 2 == 0 {
+                first[index / 2]
+            } else {
+                second[index / 2]
+            }
+        })
+        .collect())
+}
+
+fn decode_openexr(bytes: &[u8]) -> Result<RgbaF32Image, String> {
+    let mut reader = ExrReader::new(bytes, 0);
+    if reader.take(4)? != EXR_MAGIC {
+        return Err(String::from("Not an OpenEXR file."));
+    }
+    let version = reader.u32()?;
+    if version & 0xff != 2 {
+        return Err(format!("Unsupported OpenEXR version {}.", version & 0xff));
+    }
+    if version & (EXR_TILED | EXR_NON_IMAGE | EXR_MULTIPART) != 0 {
+        return Err(String::from(
+            "Only single part scan line OpenEXR files are supported.",
+        ));
+    }
+
+    let mut channels = None;
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _type_name = reader.string()?;
+        let size = reader.i32()?;
+        if size < 0 {
+            return Err(format!("OpenEXR attribute {} has a negative size.", name));
+        }
+        let value = reader.take(size as usize)?;
+        match name.as_str() {
+            "channels" => channels = Some(parse_exr_channels(value)?),
+            "compression" => compression = value.first().cloned(),
+            "dataWindow" => {
+                let mut value = ExrReader::new(value, 0);
+                data_window = Some((value.i32()?, value.i32()?, value.i32()?, value.i32()?));
+            }
+            _ => {}
+        }
+    }
+
+    let channels = channels.ok_or_else(|| String::from("OpenEXR file has no channels."))?;
+    let compression =
+        compression.ok_or_else(|| String::from("OpenEXR file has no compression."))?;
+    let (x_min, y_min, x_max, y_max) =
+        data_window.ok_or_else(|| String::from("OpenEXR file has no data window."))?;
+    if x_max < x_min || y_max < y_min {
+        return Err(String::from("OpenEXR data window is empty."));
+    }
+    if compression != EXR_NO_COMPRESSION && compression != EXR_RLE_COMPRESSION {
+        return Err(format!("Unsupported OpenEXR compression {}.", compression));
+    }
+
+    let width = x_max as i64 - x_min as i64 + 1;
+    let height = y_max as i64 - y_min as i64 + 1;
+    if width > EXR_MAX_DIMENSION || height > EXR_MAX_DIMENSION {
+        return Err(format!("OpenEXR data window {}x{} is too large.", width, height));
+    }
+    let line_size = width as usize * channels.iter().map(|c| c.pixel_type.size()).sum::<usize>();
+
+    // Every scan line takes an offset, a chunk header and its data. A run
+    // length encoded line shrinks by at most a factor 64.
+    let min_data_size = if compression == EXR_RLE_COMPRESSION {
+        line_size / 64
+    } else {
+        line_size
+    };
+    if height as u64 * (16 + min_data_size as u64) > bytes.len() as u64 {
+        return Err(format!(
+            "OpenEXR data window {}x{} does not fit in the file.",
+            width, height
+        ));
+    }
+    let width = width as u32;
+    let height = height as u32;
+
+    // Both supported compressions store one scan line per chunk.
+    let offsets = (0..height)
+        .map(|_| reader.u64())
+        .collect::<Result<Vec<u64>, String>>()?;
+
+    let mut image = RgbaF32Image::from_pixel(width, height, image::Rgba([0.0, 0.0, 0.0, 1.0]));
+    for offset in offsets {
+        let mut chunk = ExrReader::new(bytes, offset as usize);
+        let y = chunk.i32()?;
+        let size = chunk.i32()?;
+        if y < y_min || y > y_max || size < 0 {
+            return Err(format!("Invalid OpenEXR scan line {}.", y));
+        }
+        let data = chunk.take(size as usize)?;
+        // Lines that do not compress are stored as is.
+        let line = if data.len() == line_size {
+            data.to_vec()
+        } else if compression == EXR_RLE_COMPRESSION {
+            decompress_exr_rle(data, line_size)?
+        } else {
+            return Err(format!("OpenEXR scan line {} has the wrong size.", y));
+        };
+
+        let mut samples = ExrReader::new(&line, 0);
+        for channel in channels.iter() {
+            let component = match channel.name.as_str() {
+                "R" => Some(0),
+                "G" => Some(1),
+                "B" => Some(2),
+                "A" => Some(3),
+                _ => None,
+            };
+            for x in 0..width {
+                let value = match channel.pixel_type {
+                    ExrPixelType::Uint => samples.u32()? as f32,
+                    ExrPixelType::Half => {
+                        let bytes = samples.take(2)?;
+                        half_to_f32(bytes[0] as u16 | (bytes[1] as u16) << 8)
+                    }
+                    ExrPixelType::Float => f32::from_bits(samples.u32()?),
+                };
+                if let Some(component) = component {
+                    image.get_pixel_mut(x, (y - y_min) as u32).data[component] = value;
+                }
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+/// Loads a `.hdr` or `.exr` file based on its extension. `.exr` files are
+/// subject to the limitations of `load_openexr`.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<HdrImage, String> {
+    let path = path.as_ref();
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    match extension.as_ref().map(|e| e.as_str()) {
+        Some("hdr") => load_radiance(path).map(HdrImage::Rgb),
+        Some("exr") => load_openexr(path).map(HdrImage::Rgba),
+        _ => Err(format!("{} is not a .hdr or .exr file.", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_flat_radiance_pixels() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        bytes.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let image = decode_radiance(io::Cursor::new(bytes)).unwrap();
+        assert_eq!((2, 1), image.dimensions());
+        assert_eq!([1.0, 0.5, 0.0], image.get_pixel(0, 0).data);
+        assert_eq!([0.0, 0.0, 0.0], image.get_pixel(1, 0).data);
+    }
+
+    #[test]
+    fn converts_halfs() {
+        assert_eq!(1.0, half_to_f32(0x3c00));
+        assert_eq!(-2.0, half_to_f32(0xc000));
+        assert_eq!(0.5f32.powi(24), half_to_f32(0x0001));
+        assert_eq!(::std::f32::INFINITY, half_to_f32(0x7c00));
+    }
+
+    #[test]
+    fn decompresses_exr_rle() {
+        assert_eq!(Ok(vec![1, 2, 3, 4]), decompress_exr_rle(&[0xfc, 1, 130, 127, 130], 4));
+        assert_eq!(Ok(vec![5, 5, 5, 5]), decompress_exr_rle(&[0xff, 5, 2, 128], 4));
+        assert!(decompress_exr_rle(&[0xff, 5, 2, 128], 5).is_err());
+    }
+
+    fn le_bytes(value: u64, count: usize) -> Vec<u8> {
+        (0..count).map(|index| (value >> (8 * index)) as u8).collect()
+    }
+
+    fn exr_attribute(bytes: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(type_name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&le_bytes(value.len() as u64, 4));
+        bytes.extend_from_slice(value);
+    }
+
+    /// Builds the header of a half precision BGR file.
+    fn exr_header(data_window: [i32; 4]) -> Vec<u8> {
+        let mut channels = Vec::new();
+        for name in &["B", "G", "R"] {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            // Half, not linear, reserved, no subsampling.
+            channels.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        }
+        channels.push(0);
+
+        let mut bytes = EXR_MAGIC.to_vec();
+        bytes.extend_from_slice(&[2, 0, 0, 0]);
+        exr_attribute(&mut bytes, "channels", "chlist", &channels);
+        exr_attribute(&mut bytes, "compression", "compression", &[EXR_NO_COMPRESSION]);
+        let data_window = data_window
+            .iter()
+            .flat_map(|&value| le_bytes(value as u32 as u64, 4))
+            .collect::<Vec<u8>>();
+        exr_attribute(&mut bytes, "dataWindow", "box2i", &data_window);
+        bytes.push(0);
+        bytes
+    }
+
+    #[test]
+    fn decodes_uncompressed_exr_scan_lines() {
+        let mut bytes = exr_header([0, 0, 1, 0]);
+        let offset = bytes.len() as u64 + 8;
+        bytes.extend_from_slice(&le_bytes(offset, 8));
+        bytes.extend_from_slice(&[0, 0, 0, 0, 12, 0, 0, 0]);
+        // Blue, green and red samples of both pixels.
+        bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0xc0]);
+        bytes.extend_from_slice(&[0x00, 0x38, 0x00, 0x3c]);
+        bytes.extend_from_slice(&[0x00, 0x3c, 0x00, 0x00]);
+
+        let image = decode_openexr(&bytes).unwrap();
+        assert_eq!((2, 1), image.dimensions());
+        assert_eq!([1.0, 0.5, 0.0, 1.0], image.get_pixel(0, 0).data);
+        assert_eq!([0.0, 1.0, -2.0, 1.0], image.get_pixel(1, 0).data);
+    }
+
+    #[test]
+    fn rejects_exr_data_windows_larger_than_the_file() {
+        let mut bytes = exr_header([i32::MIN, 0, i32::MAX, 0]);
+        bytes.extend_from_slice(&[0; 32]);
+        assert!(decode_openexr(&bytes).is_err());
+
+        let mut bytes = exr_header([0, 0, 0, 4095]);
+        bytes.extend_from_slice(&[0; 32]);
+        assert!(decode_openexr(&bytes).is_err());
+    }
+}
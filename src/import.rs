@@ -14,6 +14,13 @@ pub struct Mesh {
     pub indices: Vec<[GLuint; 3]>,
 }
 
+impl Mesh {
+    /// The triangle indices as a flat list, as expected by `IndexBuffer::new`.
+    pub fn flat_indices(&self) -> Vec<GLuint> {
+        self.indices.iter().flat_map(|triangle| triangle.iter().cloned()).collect()
+    }
+}
+
 #[derive(PartialEq, Copy, Clone, Debug)]
 #[repr(C)]
 struct TriangleElement {
@@ -30,11 +37,14 @@ fn triangle_element_has_expected_layout() {
     assert_eq!(12, ::std::mem::size_of::<TriangleElement>());
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Vertex)]
 #[repr(C)]
 pub struct VertexData {
+    #[vertex(location = 0)]
     pub vertex_position: Vector3<GLfloat>,
+    #[vertex(location = 1)]
     pub texture_position: Vector2<GLfloat>,
+    #[vertex(location = 2)]
     pub vertex_normal: Vector3<GLfloat>,
 }
 
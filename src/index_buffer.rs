@@ -0,0 +1,275 @@
+extern crate gl;
+
+use buffer::{Buffer, BufferId, BufferTarget, BufferUsage};
+use draw::DrawMode;
+use gl::types::*;
+use std::ops::Range;
+
+/// Marks the end of a primitive in the indices passed to `IndexBuffer::new`.
+/// It is stored as the largest value of the chosen `IndexType`.
+pub const PRIMITIVE_RESTART: u32 = ::std::u32::MAX;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum IndexType {
+    UnsignedByte = gl::UNSIGNED_BYTE,
+    UnsignedShort = gl::UNSIGNED_SHORT,
+    UnsignedInt = gl::UNSIGNED_INT,
+}
+
+impl IndexType {
+    /// The narrowest type that can index `vertex_count` vertices while
+    /// keeping its largest value free for primitive restart.
+    pub fn for_vertex_count(vertex_count: usize) -> Self {
+        if vertex_count <= ::std::u8::MAX as usize {
+            IndexType::UnsignedByte
+        } else if vertex_count <= ::std::u16::MAX as usize {
+            IndexType::UnsignedShort
+        } else {
+            IndexType::UnsignedInt
+        }
+    }
+
+    /// The size of a single index in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match *self {
+            IndexType::UnsignedByte => 1,
+            IndexType::UnsignedShort => 2,
+            IndexType::UnsignedInt => 4,
+        }
+    }
+
+    /// The largest value of the type, used as primitive restart index.
+    #[inline]
+    pub fn restart_index(&self) -> GLuint {
+        match *self {
+            IndexType::UnsignedByte => ::std::u8::MAX as GLuint,
+            IndexType::UnsignedShort => ::std::u16::MAX as GLuint,
+            IndexType::UnsignedInt => ::std::u32::MAX,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum IndexStorage {
+    UnsignedByte(Buffer<u8>),
+    UnsignedShort(Buffer<u16>),
+    UnsignedInt(Buffer<u32>),
+}
+
+/// Checks that all indices either refer to one of `vertex_count` vertices
+/// or are `PRIMITIVE_RESTART`. Returns whether any restart index was found.
+fn check_indices(indices: &[u32], vertex_count: usize) -> Result<bool, String> {
+    let mut primitive_restart = false;
+    for (position, &index) in indices.iter().enumerate() {
+        if index == PRIMITIVE_RESTART {
+            primitive_restart = true;
+        } else if index as usize >= vertex_count {
+            return Err(format!(
+                "Index {} at position {} is out of range for {} vertices.",
+                index, position, vertex_count
+            ));
+        }
+    }
+    Ok(primitive_restart)
+}
+
+/// Narrows indices that were checked with `check_indices`, mapping
+/// `PRIMITIVE_RESTART` to the largest value of the narrower type.
+fn narrow<T, F>(indices: &[u32], convert: F) -> Vec<T>
+where
+    F: Fn(u32) -> T,
+    T: Copy,
+{
+    indices.iter().map(|&index| convert(index)).collect()
+}
+
+/// An element array buffer that stores its indices in the narrowest
+/// `IndexType` that fits the vertex count, and draws with that type.
+#[derive(Debug)]
+pub struct IndexBuffer {
+    storage: IndexStorage,
+    primitive_restart: bool,
+}
+
+impl IndexBuffer {
+    /// Uploads `indices` referring to `vertex_count` vertices. Indices equal
+    /// to `PRIMITIVE_RESTART` restart the primitive when drawing.
+    pub fn new(indices: &[u32], vertex_count: usize, usage: BufferUsage) -> Result<Self, String> {
+        let primitive_restart = check_indices(indices, vertex_count)?;
+
+        let storage = match IndexType::for_vertex_count(vertex_count) {
+            IndexType::UnsignedByte => {
+                let mut buffer = Buffer::new()?;
+                buffer.data(&narrow(indices, |i| i as u8), usage);
+                IndexStorage::UnsignedByte(buffer)
+            }
+            IndexType::UnsignedShort => {
+                let mut buffer = Buffer::new()?;
+                buffer.data(&narrow(indices, |i| i as u16), usage);
+                IndexStorage::UnsignedShort(buffer)
+            }
+            IndexType::UnsignedInt => {
+                let mut buffer = Buffer::new()?;
+                buffer.data(indices, usage);
+                IndexStorage::UnsignedInt(buffer)
+            }
+        };
+
+        Ok(IndexBuffer {
+            storage,
+            primitive_restart,
+        })
+    }
+
+    #[inline]
+    pub fn buffer_id(&self) -> &BufferId {
+        match self.storage {
+            IndexStorage::UnsignedByte(ref buffer) => buffer.id(),
+            IndexStorage::UnsignedShort(ref buffer) => buffer.id(),
+            IndexStorage::UnsignedInt(ref buffer) => buffer.id(),
+        }
+    }
+
+    #[inline]
+    pub fn index_type(&self) -> IndexType {
+        match self.storage {
+            IndexStorage::UnsignedByte(_) => IndexType::UnsignedByte,
+            IndexStorage::UnsignedShort(_) => IndexType::UnsignedShort,
+            IndexStorage::UnsignedInt(_) => IndexType::UnsignedInt,
+        }
+    }
+
+    /// The number of indices.
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self.storage {
+            IndexStorage::UnsignedByte(ref buffer) => buffer.len(),
+            IndexStorage::UnsignedShort(ref buffer) => buffer.len(),
+            IndexStorage::UnsignedInt(ref buffer) => buffer.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the indices contain primitive restart indices.
+    #[inline]
+    pub fn has_primitive_restart(&self) -> bool {
+        self.primitive_restart
+    }
+
+    /// Binds the buffer as the element array buffer of the bound vertex
+    /// array.
+    #[inline]
+    pub fn bind(&self) {
+        self.buffer_id().bind(BufferTarget::ElementArrayBuffer);
+    }
+
+    /// Draws the indices in `range` from the bound vertex array, binding
+    /// this buffer as its element array buffer first.
+    ///
+    /// Primitive restart is enabled, with the restart index of the index
+    /// type, for buffers that contain restart indices and disabled for all
+    /// other buffers. `GL_PRIMITIVE_RESTART` is not part of `RenderState`,
+    /// so `RenderStateApplier` does not track it and it stays as set by the
+    /// last indexed draw.
+    ///
+    /// Unsafe because the indices are not checked against the vertex
+    /// buffers of the bound vertex array.
+    pub unsafe fn draw(&self, mode: DrawMode, range: Range<usize>) -> Result<(), String> {
+        let index_type = self.prepare_draw(&range)?;
+        gl::DrawElements(
+            mode as GLenum,
+            (range.end - range.start) as GLsizei,
+            index_type as GLenum,
+            (range.start * index_type.size()) as *const GLvoid,
+        );
+        Ok(())
+    }
+
+    /// Draws `instance_count` instances of the indices in `range`. Attributes
+    /// with a non-zero divisor advance per instance. Sets primitive restart
+    /// like `draw`.
+    pub unsafe fn draw_instanced(
+        &self,
+        mode: DrawMode,
+        range: Range<usize>,
+        instance_count: usize,
+    ) -> Result<(), String> {
+        let index_type = self.prepare_draw(&range)?;
+        gl::DrawElementsInstanced(
+            mode as GLenum,
+            (range.end - range.start) as GLsizei,
+            index_type as GLenum,
+            (range.start * index_type.size()) as *const GLvoid,
+            instance_count as GLsizei,
+        );
+        Ok(())
+    }
+
+    fn prepare_draw(&self, range: &Range<usize>) -> Result<IndexType, String> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(format!(
+                "Indices {}..{} are out of range for an index buffer of {} indices.",
+                range.start,
+                range.end,
+                self.len()
+            ));
+        }
+        Ok(self.bind_for_draw())
+    }
+
+    /// Binds the buffer and enables or disables primitive restart for
+    /// drawing from it, see `draw`.
+    pub(crate) fn bind_for_draw(&self) -> IndexType {
+        self.bind();
+
+        let index_type = self.index_type();
+        unsafe {
+            if self.primitive_restart {
+                gl::Enable(gl::PRIMITIVE_RESTART);
+                gl::PrimitiveRestartIndex(index_type.restart_index());
+            } else {
+                gl::Disable(gl::PRIMITIVE_RESTART);
+            }
+        }
+        index_type
+    }
+
+    /// Draws all indices, see `draw`.
+    #[inline]
+    pub unsafe fn draw_all(&self, mode: DrawMode) -> Result<(), String> {
+        self.draw(mode, 0..self.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_type_fits_vertex_count() {
+        assert_eq!(IndexType::for_vertex_count(0), IndexType::UnsignedByte);
+        assert_eq!(IndexType::for_vertex_count(255), IndexType::UnsignedByte);
+        assert_eq!(IndexType::for_vertex_count(256), IndexType::UnsignedShort);
+        assert_eq!(IndexType::for_vertex_count(65535), IndexType::UnsignedShort);
+        assert_eq!(IndexType::for_vertex_count(65536), IndexType::UnsignedInt);
+    }
+
+    #[test]
+    fn restart_indices_narrow_to_the_largest_value() {
+        let indices = [0, 1, 2, PRIMITIVE_RESTART, 2, 1, 3];
+        assert_eq!(check_indices(&indices, 4), Ok(true));
+        assert_eq!(check_indices(&indices[..3], 4), Ok(false));
+        assert!(check_indices(&indices, 3).is_err());
+
+        let narrowed = narrow(&indices, |i| i as u8);
+        assert_eq!(narrowed[3] as GLuint, IndexType::UnsignedByte.restart_index());
+        let narrowed = narrow(&indices, |i| i as u16);
+        assert_eq!(narrowed[3] as GLuint, IndexType::UnsignedShort.restart_index());
+    }
+}
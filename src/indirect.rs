@@ -0,0 +1,239 @@
+extern crate gl;
+
+use buffer::{Buffer, BufferTarget, BufferUsage};
+use context::Capabilities;
+use draw::DrawMode;
+use gl::types::*;
+use index_buffer::IndexBuffer;
+use std::mem;
+use std::ops::Range;
+
+/// The layout `glDrawElementsIndirect` reads a single draw from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct DrawElementsIndirectCommand {
+    pub count: GLuint,
+    pub instance_count: GLuint,
+    pub first_index: GLuint,
+    pub base_vertex: GLint,
+    pub base_instance: GLuint,
+}
+
+/// Collects indexed draws for an `IndirectBuffer`.
+#[derive(Debug, Default)]
+pub struct IndirectCommandsBuilder {
+    commands: Vec<DrawElementsIndirectCommand>,
+}
+
+impl IndirectCommandsBuilder {
+    pub fn new() -> Self {
+        IndirectCommandsBuilder::default()
+    }
+
+    /// Draws the indices in `index_range` once, adding `base_vertex` to every
+    /// index. Merged meshes use the base vertex to address their own part of
+    /// a shared vertex buffer.
+    pub fn draw(&mut self, index_range: Range<usize>, base_vertex: GLint) -> &mut Self {
+        self.draw_instanced(index_range, base_vertex, 0..1)
+    }
+
+    /// Draws the indices in `index_range` for every instance in `instances`.
+    /// A non-zero instance start requires base instance support.
+    pub fn draw_instanced(
+        &mut self,
+        index_range: Range<usize>,
+        base_vertex: GLint,
+        instances: Range<usize>,
+    ) -> &mut Self {
+        self.commands.push(DrawElementsIndirectCommand {
+            count: (index_range.end - index_range.start) as GLuint,
+            instance_count: (instances.end - instances.start) as GLuint,
+            first_index: index_range.start as GLuint,
+            base_vertex,
+            base_instance: instances.start as GLuint,
+        });
+        self
+    }
+
+    pub fn commands(&self) -> &[DrawElementsIndirectCommand] {
+        &self.commands
+    }
+
+    pub fn build(&self, usage: BufferUsage) -> Result<IndirectBuffer, String> {
+        let mut buffer = Buffer::new()?;
+        buffer.data(&self.commands, usage);
+        Ok(IndirectBuffer {
+            buffer,
+            commands: self.commands.clone(),
+        })
+    }
+}
+
+/// Checks that every command reads within an index buffer of `index_count`
+/// indices.
+fn check_commands(
+    commands: &[DrawElementsIndirectCommand],
+    index_count: usize,
+) -> Result<(), String> {
+    for (position, command) in commands.iter().enumerate() {
+        let end = command.first_index as usize + command.count as usize;
+        if end > index_count {
+            return Err(format!(
+                "Command {} draws indices {}..{} but the index buffer holds {} indices.",
+                position, command.first_index, end, index_count
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A buffer of indirect draw commands. A copy of the commands is kept to
+/// validate them and to draw them one by one when indirect draws are not
+/// supported.
+#[derive(Debug)]
+pub struct IndirectBuffer {
+    buffer: Buffer<DrawElementsIndirectCommand>,
+    commands: Vec<DrawElementsIndirectCommand>,
+}
+
+impl IndirectBuffer {
+    #[inline]
+    pub fn buffer(&self) -> &Buffer<DrawElementsIndirectCommand> {
+        &self.buffer
+    }
+
+    #[inline]
+    pub fn commands(&self) -> &[DrawElementsIndirectCommand] {
+        &self.commands
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Issues the command at `index` with `glDrawElementsIndirect`, see
+    /// `multi_draw`.
+    pub unsafe fn draw(
+        &self,
+        mode: DrawMode,
+        index_buffer: &IndexBuffer,
+        index: usize,
+        capabilities: &Capabilities,
+    ) -> Result<(), String> {
+        self.multi_draw(mode, index_buffer, index..index + 1, capabilities)
+    }
+
+    /// Issues the commands in `range` from the bound vertex array, reading
+    /// indices from `index_buffer`. Uses `glMultiDrawElementsIndirect` when
+    /// available and otherwise issues the commands one at a time, falling
+    /// back to `glDrawElementsInstancedBaseVertex` without indirect draw
+    /// support. Sets primitive restart like `IndexBuffer::draw`.
+    ///
+    /// Unsafe because the indices are not checked against the vertex
+    /// buffers of the bound vertex array.
+    pub unsafe fn multi_draw(
+        &self,
+        mode: DrawMode,
+        index_buffer: &IndexBuffer,
+        range: Range<usize>,
+        capabilities: &Capabilities,
+    ) -> Result<(), String> {
+        if range.start > range.end || range.end > self.commands.len() {
+            return Err(format!(
+                "Commands {}..{} are out of range for an indirect buffer of {} commands.",
+                range.start,
+                range.end,
+                self.commands.len()
+            ));
+        }
+        let commands = &self.commands[range.clone()];
+        check_commands(commands, index_buffer.len())?;
+        if !capabilities.has_base_instance() && commands.iter().any(|c| c.base_instance != 0) {
+            return Err(String::from(
+                "Commands with a base instance require GL 4.2 or ARB_base_instance.",
+            ));
+        }
+
+        let index_type = index_buffer.bind_for_draw();
+        let stride = mem::size_of::<DrawElementsIndirectCommand>();
+
+        if capabilities.has_multi_draw_indirect() {
+            self.buffer.id().bind(BufferTarget::DrawIndirectBuffer);
+            gl::MultiDrawElementsIndirect(
+                mode as GLenum,
+                index_type as GLenum,
+                (range.start * stride) as *const GLvoid,
+                commands.len() as GLsizei,
+                stride as GLsizei,
+            );
+        } else if capabilities.has_draw_indirect() {
+            self.buffer.id().bind(BufferTarget::DrawIndirectBuffer);
+            for index in range {
+                gl::DrawElementsIndirect(
+                    mode as GLenum,
+                    index_type as GLenum,
+                    (index * stride) as *const GLvoid,
+                );
+            }
+        } else {
+            for command in commands {
+                let indices = (command.first_index as usize * index_type.size()) as *const GLvoid;
+                if command.base_instance == 0 {
+                    gl::DrawElementsInstancedBaseVertex(
+                        mode as GLenum,
+                        command.count as GLsizei,
+                        index_type as GLenum,
+                        indices,
+                        command.instance_count as GLsizei,
+                        command.base_vertex,
+                    );
+                } else {
+                    gl::DrawElementsInstancedBaseVertexBaseInstance(
+                        mode as GLenum,
+                        command.count as GLsizei,
+                        index_type as GLenum,
+                        indices,
+                        command.instance_count as GLsizei,
+                        command.base_vertex,
+                        command.base_instance,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_elements_indirect_command_has_size_20() {
+        assert_eq!(20, mem::size_of::<DrawElementsIndirectCommand>());
+    }
+
+    #[test]
+    fn builder_records_commands_and_they_are_checked() {
+        let mut builder = IndirectCommandsBuilder::new();
+        builder.draw(0..36, 0).draw_instanced(36..276, 24, 4..8);
+        assert_eq!(
+            builder.commands()[1],
+            DrawElementsIndirectCommand {
+                count: 240,
+                instance_count: 4,
+                first_index: 36,
+                base_vertex: 24,
+                base_instance: 4,
+            }
+        );
+        assert!(check_commands(builder.commands(), 276).is_ok());
+        assert!(check_commands(builder.commands(), 275).is_err());
+    }
+}
@@ -10,19 +10,36 @@ extern crate image;
 
 #[macro_use(field_offset)]
 extern crate simple_field_offset;
+#[macro_use]
+extern crate vertex_derive;
 
 pub mod id;
 pub mod camera;
 pub mod phantomdata;
+pub mod context;
 pub mod shader;
 pub mod framebuffer;
 pub mod renderbuffer;
+pub mod multisample;
+pub mod render_state;
+pub mod render_target;
 pub mod program;
 pub mod import;
+pub mod hdr;
 pub mod palette;
 pub mod texture;
-pub mod vertex_buffer;
+pub mod texture_array;
+pub mod atlas;
+pub mod mipmap;
+pub mod buffer;
+pub mod draw;
+pub mod index_buffer;
+pub mod indirect;
+pub mod fence;
+pub mod ring_buffer;
+pub mod vertex;
 pub mod vertex_array;
+pub mod gpu_mesh;
 pub mod viewport;
 
 #[macro_use]
@@ -32,29 +49,62 @@ pub mod string;
 pub mod debug;
 
 use camera::*;
+use context::*;
 // use shader::*;
 use shader::specialization::*;
 use framebuffer::*;
 use renderbuffer::*;
+use multisample::*;
+use render_state::*;
+use render_target::*;
 use program::*;
 // use import::*;
 // use palette::*;
 use texture::*;
-use vertex_buffer::*;
+use buffer::*;
+use draw::*;
 use vertex_array::*;
+use gpu_mesh::*;
 use viewport::*;
 
 use cgmath::prelude::*;
 use cgmath::*;
-use glutin::GlContext;
+use glutin::GlContext as GlutinGlContext;
 use std::path::Path;
 use std::io::Read;
 use gl::types::*;
 use std::time;
-use std::mem;
 use std::io;
 use std::fs;
 
+#[derive(Clone, Copy, Vertex)]
+#[repr(C)]
+struct PostVertex {
+    #[vertex(location = 0)]
+    vertex_position: [GLfloat; 2],
+    #[vertex(location = 1)]
+    texture_position: [GLfloat; 2],
+}
+
+#[derive(Clone, Copy, Vertex)]
+#[repr(C)]
+struct LightInstance {
+    #[vertex(location = 3, divisor = 1)]
+    pos_from_obj_to_wld_space: Matrix4<GLfloat>,
+    #[vertex(location = 7, divisor = 1)]
+    color: Vector3<GLfloat>,
+}
+
+impl LightInstance {
+    fn new(light: &PointLight) -> Self {
+        LightInstance {
+            pos_from_obj_to_wld_space: Matrix4::from_translation(light.position)
+                * Matrix4::from_scale(0.2),
+            color: light.color.diffuse,
+        }
+    }
+}
+
 struct LightColor {
     ambient: Vector3<f32>,
     diffuse: Vector3<f32>,
@@ -170,6 +220,7 @@ fn duration_to_seconds(duration: time::Duration) -> f64 {
 fn main() {
     const INITIAL_NEAR: GLfloat = 0.1;
     const INITIAL_FAR: GLfloat = 100.0;
+    const MSAA_SAMPLES: u32 = 4;
 
     let mut viewport = Viewport::new(1024, 768);
 
@@ -193,14 +244,14 @@ fn main() {
         gl_window.make_current().unwrap();
     }
 
-    gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+    let mut context = GlContext::new(|symbol| gl_window.get_proc_address(symbol) as *const _)
+        .unwrap();
 
-    let mut texture_unit_slot = TextureUnitSlot;
-    let mut program_slot = ProgramSlot {};
-    let mut renderbuffer_slot = RenderbufferSlot;
-    let mut renderbuffer_target = renderbuffer_slot.target();
-    let mut draw_framebuffer_slot = DrawFramebufferSlot {};
-    let mut read_framebuffer_slot = ReadFramebufferSlot {};
+    let scene_render_state = RenderState {
+        depth: Some(DepthState::default()),
+        ..RenderState::default()
+    };
+    let post_render_state = RenderState::default();
 
     let program = {
         let vertex_src = file_to_string("assets/standard.vert").unwrap();
@@ -223,61 +274,7 @@ fn main() {
 
     let mesh = import::import_obj("assets/crate.obj").expect("Failed to import crate.obj");
 
-    let va = VertexArrayId::new().unwrap();
-    let vb = VertexBufferId::new().unwrap();
-    let ve = VertexBufferId::new().unwrap();
-
-    unsafe {
-        va.bind();
-
-        vb.bind(BufferTarget::ArrayBuffer);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            mem::size_of_val(&mesh.elements[..]) as GLsizeiptr,
-            mesh.elements.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW,
-        );
-
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            mem::size_of::<import::VertexData>() as GLsizei,
-            field_offset!(import::VertexData, vertex_position) as *const GLvoid,
-        );
-        gl::EnableVertexAttribArray(0);
-
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            mem::size_of::<import::VertexData>() as GLsizei,
-            field_offset!(import::VertexData, texture_position) as *const GLvoid,
-        );
-        gl::EnableVertexAttribArray(1);
-
-        gl::VertexAttribPointer(
-            2,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            mem::size_of::<import::VertexData>() as GLsizei,
-            field_offset!(import::VertexData, vertex_normal) as *const GLvoid,
-        );
-        gl::EnableVertexAttribArray(2);
-
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ve.as_uint());
-
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            mem::size_of_val(&mesh.indices[..]) as GLsizeiptr,
-            mesh.indices.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW,
-        );
-    }
+    let crate_mesh = GpuMesh::from_mesh(context.capabilities(), &mesh).unwrap();
 
     let diffuse_texture_id: TextureId = {
         let img = image::open("assets/crate_diffuse.png").unwrap();
@@ -285,33 +282,17 @@ fn main() {
         let img = img.flipv().to_rgba();
 
         let mut diffuse_texture_id = TextureId::new().unwrap();
-        unsafe {
-            // Each row is expected to be padded to be a multiple of
-            // GL_UNPACK_ALIGNMENT which is 4 by default. Here we set it to
-            // 1 which means the rows will not be padded.
-
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-
-            texture_unit_slot
-                .activate(TextureUnit::TextureUnit0)
-                .texture_slot_2d
-                .target()
-                .bind(&diffuse_texture_id)
-                .min_filter(TextureFilter::LinearMipmapLinear)
-                .mag_filter(TextureFilter::LinearMipmapLinear)
-                .wrap_s(gl::REPEAT as GLint)
-                .wrap_t(gl::REPEAT as GLint)
-                .image_2d(
-                    0,                             // MIP map level
-                    gl::RGBA8 as GLint,            // internal format
-                    img.width() as GLint,          // width
-                    img.height() as GLint,         // height
-                    gl::RGBA,                      // format
-                    gl::UNSIGNED_BYTE,             // component format
-                    img.as_ptr() as *const GLvoid, // data
-                )
-                .generate_mipmap();
-        }
+        context.texture_unit_slot
+            .activate(TextureUnit::TextureUnit0)
+            .texture_slot_2d
+            .target()
+            .bind(&diffuse_texture_id)
+            .min_filter(TextureFilter::LinearMipmapLinear)
+            .mag_filter(TextureFilter::LinearMipmapLinear)
+            .wrap_s(gl::REPEAT as GLint)
+            .wrap_t(gl::REPEAT as GLint)
+            .image_2d_from(0, gl::RGBA8 as GLint, &img)
+            .generate_mipmap();
 
         diffuse_texture_id
     };
@@ -322,37 +303,22 @@ fn main() {
         let img = img.flipv().to_rgba();
 
         let mut specular_texture_id = TextureId::new().unwrap();
-        unsafe {
-            // Each row is expected to be padded to be a multiple of
-            // GL_UNPACK_ALIGNMENT which is 4 by default. Here we set it to
-            // 1 which means the rows will not be padded.
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-
-            texture_unit_slot
-                .activate(TextureUnit::TextureUnit0)
-                .texture_slot_2d
-                .target()
-                .bind(&specular_texture_id)
-                .min_filter(TextureFilter::LinearMipmapLinear)
-                .mag_filter(TextureFilter::LinearMipmapLinear)
-                .wrap_s(gl::REPEAT as GLint)
-                .wrap_t(gl::REPEAT as GLint)
-                .image_2d(
-                    0,                             // MIP map level
-                    gl::RGBA8 as GLint,            // internal format
-                    img.width() as GLint,          // width
-                    img.height() as GLint,         // height
-                    gl::RGBA,                      // format
-                    gl::UNSIGNED_BYTE,             // component format
-                    img.as_ptr() as *const GLvoid, // data
-                )
-                .generate_mipmap();
-        }
+        context.texture_unit_slot
+            .activate(TextureUnit::TextureUnit0)
+            .texture_slot_2d
+            .target()
+            .bind(&specular_texture_id)
+            .min_filter(TextureFilter::LinearMipmapLinear)
+            .mag_filter(TextureFilter::LinearMipmapLinear)
+            .wrap_s(gl::REPEAT as GLint)
+            .wrap_t(gl::REPEAT as GLint)
+            .image_2d_from(0, gl::RGBA8 as GLint, &img)
+            .generate_mipmap();
 
         specular_texture_id
     };
 
-    program_slot
+    context.program_slot
         .bind(&program)
         // Set texture units.
         .set_uniform_1i(&program.uniform_location(static_cstr!("material.diffuse")), 0)
@@ -434,106 +400,88 @@ fn main() {
 
     let light_mesh = import::import_obj("assets/icosphere-80.obj").expect("Failed to import obj");
 
-    let light_vertex_array = VertexArrayId::new().unwrap();
-    let light_vertex_buffer = VertexBufferId::new().unwrap();
-    let light_elements_buffer = VertexBufferId::new().unwrap();
+    let mut light_gpu_mesh = GpuMesh::from_mesh(context.capabilities(), &light_mesh).unwrap();
 
-    unsafe {
-        light_vertex_array.bind();
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, light_vertex_buffer.as_uint());
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            mem::size_of_val(&light_mesh.elements[..]) as GLsizeiptr,
-            light_mesh.elements.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW,
-        );
-
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            mem::size_of::<import::VertexData>() as GLsizei,
-            field_offset!(import::VertexData, vertex_position) as *const GLvoid,
-        );
-        gl::EnableVertexAttribArray(0);
-
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, light_elements_buffer.as_uint());
-
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            mem::size_of_val(&light_mesh.indices[..]) as GLsizeiptr,
-            light_mesh.indices.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW,
-        );
-    }
-
-    let main_fb_tex = TextureId::new().unwrap();
-
-    unsafe {
-        // let mut atu = texture_unit_slot.activate(TextureUnit::TextureUnit0);
-        // let mut bound_main_fb_tex = atu.texture_slot_2d.target().bind(&main_fb_tex);
-        texture_unit_slot
-            .activate(TextureUnit::TextureUnit0)
-            .texture_slot_2d
-            .target()
-            .bind(&main_fb_tex)
-            .min_filter(TextureFilter::Nearest)
-            .mag_filter(TextureFilter::Nearest)
-            .wrap_s(gl::CLAMP_TO_EDGE as GLint)
-            .wrap_t(gl::CLAMP_TO_EDGE as GLint)
-            .image_2d(
-                0,                 // MIP map level
-                gl::RGB8 as GLint, // internal format
-                viewport.width(),
-                viewport.height(),
-                gl::RGB,           // format
-                gl::UNSIGNED_BYTE, // component format
-                std::ptr::null(),  // data
-            );
-    }
-
-    let main_fb_depth_stencil = RenderbufferId::new().unwrap();
-
-    // Set the internal format, width and height.
-    renderbuffer_target.bind(&main_fb_depth_stencil).storage(
-        RenderbufferInternalFormat::DEPTH24_STENCIL8,
-        viewport.width(),
-        viewport.height(),
+    let mut light_instance_buffer = Buffer::new().unwrap();
+    light_instance_buffer.data(
+        &point_lights.iter().map(LightInstance::new).collect::<Vec<_>>(),
+        BufferUsage::DynamicDraw,
     );
 
-    // Create a framebuffer to render to.
-    let main_fb = FramebufferId::new().unwrap();
+    light_gpu_mesh
+        .configure_instances(&light_instance_buffer)
+        .unwrap();
+
+    let msaa_samples = SampleCount::new(MSAA_SAMPLES).unwrap_or_else(|err| {
+        println!("{} Using the maximum instead.", err);
+        SampleCount::max()
+    });
+
+    // Create a multisampled render target to render to.
+    let mut msaa_target = RenderTarget::new(
+        &context,
+        RenderTargetSize::Full,
+        Some(msaa_samples),
+        &[
+            (
+                FramebufferAttachment::Color0,
+                AttachmentStorage::Renderbuffer(RenderbufferInternalFormat::RGB8),
+            ),
+            (
+                FramebufferAttachment::DepthStencil,
+                AttachmentStorage::Renderbuffer(RenderbufferInternalFormat::DEPTH24_STENCIL8),
+            ),
+        ],
+    ).unwrap();
 
-    unsafe {
-        DrawReadFramebufferTarget::new(&mut draw_framebuffer_slot, &mut read_framebuffer_slot)
-            .bind(&main_fb)
-            .attach_texture_2d(
-                FramebufferAttachment::color(0),
-                gl::TEXTURE_2D,
-                &main_fb_tex,
-                0,
-            )
-            .attach_renderbuffer(FramebufferAttachment::DepthStencil, &main_fb_depth_stencil);
-    }
+    msaa_target
+        .update(
+            &viewport,
+            &mut context,
+        )
+        .expect("Multisampled framebuffer not complete");
+
+    // Create a render target to resolve the multisampled render target into.
+    let mut main_target = RenderTarget::new(
+        &context,
+        RenderTargetSize::Full,
+        None,
+        &[(
+            FramebufferAttachment::Color0,
+            AttachmentStorage::Texture(TextureStorage {
+                internal_format: gl::RGB8,
+                format: gl::RGB,
+                component_format: gl::UNSIGNED_BYTE,
+            }),
+        )],
+    ).unwrap();
 
-    // FIXME
-    match unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) } {
-        gl::FRAMEBUFFER_COMPLETE => {}
-        _ => {
-            panic!("Framebuffer not complete");
-        }
-    }
+    main_target
+        .update(
+            &viewport,
+            &mut context,
+        )
+        .expect("Framebuffer not complete");
 
     let post_vao = VertexArrayId::new().unwrap();
-    let post_vbo = VertexBufferId::new().unwrap();
-    let post_vertex_data: [GLfloat; 16] = [
-        -1.0, -1.0, 0.0, 0.0, // vertex coordinates, texture coordinates
-        1.0, -1.0, 1.0, 0.0, //
-        -1.0, 1.0, 0.0, 1.0, //
-        1.0, 1.0, 1.0, 1.0, //
+    let mut post_vbo = Buffer::new().unwrap();
+    let post_vertex_data = [
+        PostVertex {
+            vertex_position: [-1.0, -1.0],
+            texture_position: [0.0, 0.0],
+        },
+        PostVertex {
+            vertex_position: [1.0, -1.0],
+            texture_position: [1.0, 0.0],
+        },
+        PostVertex {
+            vertex_position: [-1.0, 1.0],
+            texture_position: [0.0, 1.0],
+        },
+        PostVertex {
+            vertex_position: [1.0, 1.0],
+            texture_position: [1.0, 1.0],
+        },
     ];
     let post_program = {
         let vertex_shader = VertexShaderId::new()
@@ -550,7 +498,7 @@ fn main() {
             .unwrap()
     };
 
-    program_slot
+    context.program_slot
         .bind(&post_program)
         .set_uniform_1f(
             &post_program.uniform_location(static_cstr!("dx")),
@@ -561,38 +509,9 @@ fn main() {
             1.0 / viewport.height() as f32,
         );
 
-    unsafe {
-        post_vao.bind();
-
-        post_vbo.bind(BufferTarget::ArrayBuffer);
+    post_vbo.data(&post_vertex_data, BufferUsage::StaticDraw);
 
-        gl::BufferData(
-            BufferTarget::ArrayBuffer as GLenum,
-            mem::size_of_val(&post_vertex_data[..]) as GLsizeiptr,
-            post_vertex_data.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW,
-        );
-
-        gl::VertexAttribPointer(
-            0,                                         // attribute index
-            2,                                         // count
-            gl::FLOAT,                                 // type
-            gl::FALSE,                                 // normalize
-            mem::size_of::<[GLfloat; 4]>() as GLsizei, // stride
-            0 as *const GLvoid,                        // offset
-        );
-        gl::EnableVertexAttribArray(0);
-
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            mem::size_of::<[GLfloat; 4]>() as GLsizei, // stride
-            mem::size_of::<[GLfloat; 2]>() as *const GLvoid, // offset
-        );
-        gl::EnableVertexAttribArray(1);
-    }
+    post_vao.configure(&post_vbo);
 
     let start = time::Instant::now();
     let mut running = true;
@@ -618,6 +537,7 @@ fn main() {
     };
 
     let mut has_focus = true;
+    let mut take_screenshot = false;
 
     while running {
         let now = time::Instant::now();
@@ -655,32 +575,21 @@ fn main() {
                             gl_window.resize(w, h);
                             viewport.update().width(w as GLsizei).height(h as GLsizei);
 
-                            // Update framebuffer color texture size.
-                            unsafe {
-                                texture_unit_slot
-                                    .activate(TextureUnit::TextureUnit0)
-                                    .texture_slot_2d
-                                    .target()
-                                    .bind(&main_fb_tex)
-                                    .image_2d(
-                                        0,                 // MIP map level
-                                        gl::RGB8 as GLint, // internal format
-                                        viewport.width(),
-                                        viewport.height(),
-                                        gl::RGB,           // format
-                                        gl::UNSIGNED_BYTE, // component format
-                                        std::ptr::null(),  // data
-                                    );
+                            // Reallocate the render targets. A target that
+                            // fails to resize keeps rendering at its old size.
+                            if let Err(err) = msaa_target.update(&viewport, &mut context) {
+                                println!(
+                                    "Failed to resize the multisampled render target: {}",
+                                    err
+                                );
                             }
 
-                            renderbuffer_target.bind(&main_fb_depth_stencil).storage(
-                                RenderbufferInternalFormat::DEPTH24_STENCIL8,
-                                viewport.width(),
-                                viewport.height(),
-                            );
+                            if let Err(err) = main_target.update(&viewport, &mut context) {
+                                println!("Failed to resize the render target: {}", err);
+                            }
 
                             // Update uniforms dependent on viewport size.
-                            program_slot
+                            context.program_slot
                                 .bind(&post_program)
                                 .set_uniform_1f(
                                     &post_program.uniform_location(static_cstr!("dx")),
@@ -710,6 +619,11 @@ fn main() {
                                 Some(VirtualKeyCode::D) => move_right = pressed,
                                 Some(VirtualKeyCode::Q)=> move_up = pressed,
                                 Some(VirtualKeyCode::Z) => move_down = pressed,
+                                Some(VirtualKeyCode::F12) => {
+                                    if pressed {
+                                        take_screenshot = true;
+                                    }
+                                }
                                 _ => (),
                             }
                         }
@@ -767,30 +681,30 @@ fn main() {
 
         // Render.
         unsafe {
-            let _bound_fb = DrawReadFramebufferTarget::new(
-                &mut draw_framebuffer_slot,
-                &mut read_framebuffer_slot,
-            ).bind(&main_fb);
+            DrawReadFramebufferTarget::new(
+                &mut context.draw_framebuffer_slot,
+                &mut context.read_framebuffer_slot,
+            ).bind(msaa_target.framebuffer_id())
+                .clear_color_f(0, [0.7, 0.8, 0.9, 1.0])
+                .clear_depth_stencil(1.0, 0);
 
-            gl::ClearColor(0.7, 0.8, 0.9, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-            gl::Enable(gl::DEPTH_TEST);
+            context.render_state.apply(&scene_render_state);
 
-            texture_unit_slot
+            context.texture_unit_slot
                 .activate(TextureUnit::TextureUnit0)
                 .texture_slot_2d
                 .target()
                 .bind(&diffuse_texture_id)
                 .persist();
 
-            texture_unit_slot
+            context.texture_unit_slot
                 .activate(TextureUnit::TextureUnit1)
                 .texture_slot_2d
                 .target()
                 .bind(&specular_texture_id)
                 .persist();
 
-            let _bound_program = program_slot.bind(&program);
+            let bound_program = context.program_slot.bind(&program);
 
             {
                 let pos_from_obj_to_wld_space = Matrix4::from_translation(Vector3::zero())
@@ -833,79 +747,106 @@ fn main() {
                 light.set_standard_program_uniforms(&program, i, &pos_from_wld_to_cam_space);
             }
 
-            va.bind();
-
-            gl::DrawElements(
-                gl::TRIANGLES,
-                (3 * mesh.indices.len()) as GLsizei,
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-            );
+            crate_mesh.draw(&bound_program).unwrap();
         }
 
+        light_instance_buffer
+            .sub_data(0, &point_lights.iter().map(LightInstance::new).collect::<Vec<_>>())
+            .unwrap();
+
         unsafe {
             // Draw point lights.
-            let _bound_program = program_slot.bind(&light_program);
-
-            light_vertex_array.bind();
-
-            for light in point_lights.iter() {
-                let pos_from_obj_to_wld_space =
-                    Matrix4::from_translation(light.position) * Matrix4::from_scale(0.2);
-                let pos_from_obj_to_cam_space =
-                    pos_from_wld_to_cam_space * pos_from_obj_to_wld_space;
-                let pos_from_obj_to_clp_space =
-                    pos_from_cam_to_clp_space * pos_from_obj_to_cam_space;
-
-                {
-                    let loc = gl::GetUniformLocation(
-                        light_program.as_uint(),
-                        gl_str!("pos_from_obj_to_clp_space"),
-                    );
-                    gl::UniformMatrix4fv(loc, 1, gl::FALSE, pos_from_obj_to_clp_space.as_ptr());
-                }
-
-                {
-                    let loc = gl::GetUniformLocation(light_program.as_uint(), gl_str!("color"));
-                    gl::Uniform3f(
-                        loc,
-                        light.color.diffuse.x,
-                        light.color.diffuse.y,
-                        light.color.diffuse.z,
-                    );
-                }
+            let bound_program = context.program_slot.bind(&light_program);
 
-                gl::DrawElements(
-                    gl::TRIANGLES,
-                    (3 * light_mesh.indices.len()) as GLsizei,
-                    gl::UNSIGNED_INT,
-                    std::ptr::null(),
+            {
+                let pos_from_wld_to_clp_space =
+                    pos_from_cam_to_clp_space * pos_from_wld_to_cam_space;
+                let loc = gl::GetUniformLocation(
+                    light_program.as_uint(),
+                    gl_str!("pos_from_wld_to_clp_space"),
                 );
+                gl::UniformMatrix4fv(loc, 1, gl::FALSE, pos_from_wld_to_clp_space.as_ptr());
             }
+
+            // The instance buffer holds one element per point light.
+            light_gpu_mesh
+                .draw_instanced(&bound_program, point_lights.len())
+                .unwrap();
+        }
+
+        {
+            // Resolve the multisampled framebuffer. Both targets are created
+            // with matching formats, so the blit is not checked every frame.
+            let bound_read_fb = ReadFramebufferTarget::new(&mut context.read_framebuffer_slot)
+                .bind(msaa_target.framebuffer_id());
+            let mut bound_draw_fb = DrawFramebufferTarget::new(&mut context.draw_framebuffer_slot)
+                .bind(main_target.framebuffer_id());
+
+            bound_read_fb
+                .blit_to_unchecked(
+                    &mut bound_draw_fb,
+                    Rect::from_size(msaa_target.width(), msaa_target.height()),
+                    Rect::from_size(main_target.width(), main_target.height()),
+                    BlitMask::COLOR,
+                    BlitFilter::Nearest,
+                )
+                .expect("Failed to resolve the multisampled framebuffer");
         }
 
         unsafe {
             // Render offscreen buffer.
             let _bound_fb = DrawReadFramebufferTarget::new(
-                &mut draw_framebuffer_slot,
-                &mut read_framebuffer_slot,
+                &mut context.draw_framebuffer_slot,
+                &mut context.read_framebuffer_slot,
             ).bind(&DEFAULT_FRAMEBUFFER_ID);
 
-            // gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
             // gl::ClearColor(0.0, 1.0, 0.0, 1.0);
             // gl::Clear(gl::COLOR_BUFFER_BIT);
-            gl::Disable(gl::DEPTH_TEST);
-            let _bound_program = program_slot.bind(&post_program);
+            context.render_state.apply(&post_render_state);
+            let _bound_program = context.program_slot.bind(&post_program);
             post_vao.bind();
 
-            texture_unit_slot
+            context.texture_unit_slot
                 .activate(TextureUnit::TextureUnit0)
                 .texture_slot_2d
                 .target()
-                .bind(&main_fb_tex)
+                .bind(
+                    main_target
+                        .texture(FramebufferAttachment::Color0)
+                        .unwrap(),
+                )
                 .persist();
 
-            gl::DrawArrays(gl::TRIANGLE_STRIP, 0 as GLint, 4 as GLsizei);
+            draw_arrays(DrawMode::TriangleStrip, 0..4);
+        }
+
+        if take_screenshot {
+            take_screenshot = false;
+
+            let bound_read_fb = ReadFramebufferTarget::new(&mut context.read_framebuffer_slot)
+                .bind(&DEFAULT_FRAMEBUFFER_ID);
+
+            let elapsed = time::SystemTime::now()
+                .duration_since(time::UNIX_EPOCH)
+                .unwrap();
+            let timestamp = elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64;
+            // Never overwrite an earlier screenshot taken in the same
+            // millisecond.
+            let path = (0..)
+                .map(|count| match count {
+                    0 => format!("screenshot-{}.png", timestamp),
+                    _ => format!("screenshot-{}-{}.png", timestamp, count),
+                })
+                .find(|path| !Path::new(path).exists())
+                .unwrap();
+
+            match bound_read_fb
+                .read_pixels::<image::Rgb<u8>>(Rect::from_size(viewport.width(), viewport.height()))
+                .and_then(|pixels| pixels.save(&path).map_err(|e| e.to_string()))
+            {
+                Ok(()) => println!("Saved {}.", path),
+                Err(err) => println!("Failed to save {}: {}", path, err),
+            }
         }
 
         gl_window.swap_buffers().unwrap();
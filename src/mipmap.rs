@@ -0,0 +1,475 @@
+extern crate gl;
+extern crate image;
+
+use gl::types::*;
+use std::f32::consts::PI;
+use texture::{with_tight_rows, BoundTextureId, TextureTargetGroup2D};
+
+/// The kernel used to compute each mip level from the previous one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MipmapFilter {
+    /// Averages 2x2 blocks. Cheap, but blurs and aliases.
+    Box,
+    /// Kaiser windowed sinc with a radius of 3 and alpha of 4. Sharper than
+    /// box with little ringing.
+    Kaiser,
+    /// Lanczos windowed sinc with 3 lobes. Sharpest, but rings at hard
+    /// edges.
+    Lanczos,
+}
+
+impl MipmapFilter {
+    /// Filter support in destination pixels.
+    fn radius(&self) -> f32 {
+        match *self {
+            MipmapFilter::Box => 0.5,
+            MipmapFilter::Kaiser => 3.0,
+            MipmapFilter::Lanczos => 3.0,
+        }
+    }
+
+    /// Filter weight at distance `t`, measured in destination pixels.
+    fn weight(&self, t: f32) -> f32 {
+        let t = t.abs();
+        match *self {
+            MipmapFilter::Box => if t <= 0.5 { 1.0 } else { 0.0 },
+            MipmapFilter::Kaiser => {
+                const ALPHA: f32 = 4.0;
+                let r = t / self.radius();
+                if r >= 1.0 {
+                    0.0
+                } else {
+                    sinc(t) * bessel_i0(ALPHA * (1.0 - r * r).sqrt()) / bessel_i0(ALPHA)
+                }
+            }
+            MipmapFilter::Lanczos => {
+                let a = self.radius();
+                if t >= a {
+                    0.0
+                } else {
+                    sinc(t) * sinc(t / a)
+                }
+            }
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        let px = PI * x;
+        px.sin() / px
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x_squared = x * x / 4.0;
+    for k in 1..32 {
+        term *= half_x_squared / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-8 {
+            break;
+        }
+    }
+    sum
+}
+
+/// How the color channels of an image are encoded. Alpha is always linear.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+impl ColorSpace {
+    fn decode(&self, value: u8) -> f32 {
+        let v = value as f32 / 255.0;
+        match *self {
+            ColorSpace::Linear => v,
+            ColorSpace::Srgb => if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            },
+        }
+    }
+
+    fn encode(&self, value: f32) -> u8 {
+        let v = clamp01(value);
+        let v = match *self {
+            ColorSpace::Linear => v,
+            ColorSpace::Srgb => if v <= 0.0031308 {
+                v * 12.92
+            } else {
+                1.055 * v.powf(1.0 / 2.4) - 0.055
+            },
+        };
+        (v * 255.0 + 0.5) as u8
+    }
+
+    fn internal_format(&self) -> GLenum {
+        match *self {
+            ColorSpace::Linear => gl::RGBA8,
+            ColorSpace::Srgb => gl::SRGB8_ALPHA8,
+        }
+    }
+}
+
+fn clamp01(v: f32) -> f32 {
+    if v < 0.0 {
+        0.0
+    } else if v > 1.0 {
+        1.0
+    } else {
+        v
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MipmapOptions {
+    pub filter: MipmapFilter,
+    pub color_space: ColorSpace,
+    /// When set, the alpha of every level is scaled so that the fraction
+    /// of texels passing this alpha test matches the base level. Keeps
+    /// alpha-tested foliage and fences from thinning out in the distance.
+    pub alpha_cutoff: Option<f32>,
+}
+
+impl Default for MipmapOptions {
+    fn default() -> Self {
+        MipmapOptions {
+            filter: MipmapFilter::Kaiser,
+            color_space: ColorSpace::Srgb,
+            alpha_cutoff: None,
+        }
+    }
+}
+
+/// An image in linear floating point RGBA used as intermediate storage so
+/// that levels are not requantized before computing the next one. Colors
+/// are premultiplied by alpha so that transparent texels do not contribute
+/// color when filtering.
+#[derive(Clone, Debug)]
+struct LinearImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl LinearImage {
+    fn decode(image: &image::RgbaImage, color_space: ColorSpace) -> Self {
+        LinearImage {
+            width: image.width(),
+            height: image.height(),
+            pixels: image
+                .pixels()
+                .map(|p| {
+                    let alpha = p.data[3] as f32 / 255.0;
+                    [
+                        color_space.decode(p.data[0]) * alpha,
+                        color_space.decode(p.data[1]) * alpha,
+                        color_space.decode(p.data[2]) * alpha,
+                        alpha,
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    fn encode(&self, color_space: ColorSpace, alpha_scale: f32) -> image::RgbaImage {
+        let mut image = image::RgbaImage::new(self.width, self.height);
+        for (p, q) in image.pixels_mut().zip(self.pixels.iter()) {
+            // Fully transparent texels have no color left to recover.
+            let inverse_alpha = if q[3] > 1e-6 { 1.0 / q[3] } else { 0.0 };
+            p.data = [
+                color_space.encode(q[0] * inverse_alpha),
+                color_space.encode(q[1] * inverse_alpha),
+                color_space.encode(q[2] * inverse_alpha),
+                ColorSpace::Linear.encode(q[3] * alpha_scale),
+            ];
+        }
+        image
+    }
+
+    /// Resamples along one axis. `horizontal` selects the axis.
+    fn resample(&self, filter: MipmapFilter, new_len: u32, horizontal: bool) -> LinearImage {
+        let (len, lines) = if horizontal {
+            (self.width, self.height)
+        } else {
+            (self.height, self.width)
+        };
+        let (width, height) = if horizontal {
+            (new_len, self.height)
+        } else {
+            (self.width, new_len)
+        };
+
+        let scale = len as f32 / new_len as f32;
+        let support = filter.radius() * scale;
+
+        // Weights only depend on the destination index.
+        let taps: Vec<Vec<(u32, f32)>> = (0..new_len)
+            .map(|i| {
+                let center = (i as f32 + 0.5) * scale - 0.5;
+                let first = (center - support).ceil() as i64;
+                let last = (center + support).floor() as i64;
+                let mut taps: Vec<(u32, f32)> = (first..last + 1)
+                    .map(|s| {
+                        let clamped = ::std::cmp::min(::std::cmp::max(s, 0), len as i64 - 1);
+                        (clamped as u32, filter.weight((s as f32 - center) / scale))
+                    })
+                    .filter(|&(_, w)| w != 0.0)
+                    .collect();
+                let total: f32 = taps.iter().map(|&(_, w)| w).sum();
+                for tap in taps.iter_mut() {
+                    tap.1 /= total;
+                }
+                taps
+            })
+            .collect();
+
+        let mut pixels = vec![[0.0; 4]; (width * height) as usize];
+        for line in 0..lines {
+            for (i, taps) in taps.iter().enumerate() {
+                let mut acc = [0.0f32; 4];
+                for &(s, w) in taps {
+                    let p = if horizontal {
+                        self.pixels[(line * self.width + s) as usize]
+                    } else {
+                        self.pixels[(s * self.width + line) as usize]
+                    };
+                    for c in 0..4 {
+                        acc[c] += w * p[c];
+                    }
+                }
+                let index = if horizontal {
+                    line * width + i as u32
+                } else {
+                    i as u32 * width + line
+                };
+                pixels[index as usize] = acc;
+            }
+        }
+
+        LinearImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn downsample(&self, filter: MipmapFilter) -> LinearImage {
+        let width = ::std::cmp::max(1, self.width / 2);
+        let height = ::std::cmp::max(1, self.height / 2);
+        self.resample(filter, width, true)
+            .resample(filter, height, false)
+    }
+
+    fn coverage(&self, cutoff: f32, alpha_scale: f32) -> f32 {
+        let passing = self.pixels
+            .iter()
+            .filter(|p| clamp01(p[3] * alpha_scale) > cutoff)
+            .count();
+        passing as f32 / self.pixels.len() as f32
+    }
+
+    /// Finds the alpha scale for which the coverage is closest to the
+    /// desired coverage with a binary search.
+    fn alpha_scale_for_coverage(&self, cutoff: f32, desired: f32) -> f32 {
+        let mut low = 0.0;
+        let mut high = 4.0;
+        for _ in 0..16 {
+            let mid = (low + high) / 2.0;
+            if self.coverage(cutoff, mid) < desired {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        // Coverage changes in steps of whole texels, pick the closest side.
+        if desired - self.coverage(cutoff, low) < self.coverage(cutoff, high) - desired {
+            low
+        } else {
+            high
+        }
+    }
+}
+
+/// A full mip chain computed on the CPU.
+#[derive(Debug)]
+pub struct MipChain {
+    levels: Vec<image::RgbaImage>,
+    color_space: ColorSpace,
+}
+
+impl MipChain {
+    /// Computes every level down to 1x1. Filtering happens in linear space
+    /// regardless of the color space of the image.
+    pub fn generate(base: &image::RgbaImage, options: &MipmapOptions) -> Self {
+        let mut levels = vec![base.clone()];
+
+        let mut current = LinearImage::decode(base, options.color_space);
+        let base_coverage = options
+            .alpha_cutoff
+            .map(|cutoff| current.coverage(cutoff, 1.0));
+
+        while current.width > 1 || current.height > 1 {
+            current = current.downsample(options.filter);
+
+            let alpha_scale = match (options.alpha_cutoff, base_coverage) {
+                (Some(cutoff), Some(coverage)) => {
+                    current.alpha_scale_for_coverage(cutoff, coverage)
+                }
+                _ => 1.0,
+            };
+
+            levels.push(current.encode(options.color_space, alpha_scale));
+        }
+
+        MipChain {
+            levels,
+            color_space: options.color_space,
+        }
+    }
+
+    #[inline]
+    pub fn levels(&self) -> &[image::RgbaImage] {
+        &self.levels
+    }
+
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Uploads every level explicitly. sRGB chains are stored in an sRGB
+    /// internal format so sampling converts back to linear.
+    pub fn upload<'t, 'i, TTextureTarget: 't + TextureTargetGroup2D>(
+        &self,
+        texture: &mut BoundTextureId<'t, 'i, TTextureTarget>,
+    ) {
+        unsafe {
+            with_tight_rows(gl::UNPACK_ALIGNMENT, || {
+                for (level, image) in self.levels.iter().enumerate() {
+                    texture.image_2d(
+                        level as GLint,
+                        self.color_space.internal_format() as GLint,
+                        image.width() as GLint,
+                        image.height() as GLint,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        image.as_ptr() as *const GLvoid,
+                    );
+                }
+            });
+        }
+
+        texture
+            .base_level(0)
+            .max_level(self.levels.len() as GLint - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_levels_down_to_1x1() {
+        let base = image::RgbaImage::new(8, 2);
+        let chain = MipChain::generate(&base, &MipmapOptions::default());
+        let sizes: Vec<_> = chain.levels().iter().map(|l| l.dimensions()).collect();
+        assert_eq!(vec![(8, 2), (4, 1), (2, 1), (1, 1)], sizes);
+    }
+
+    #[test]
+    fn filters_preserve_constant_images() {
+        for &filter in &[MipmapFilter::Box, MipmapFilter::Kaiser, MipmapFilter::Lanczos] {
+            let base = image::RgbaImage::from_pixel(8, 8, image::Rgba([200, 100, 50, 255]));
+            let chain = MipChain::generate(&base, &MipmapOptions {
+                filter,
+                ..MipmapOptions::default()
+            });
+            for level in chain.levels() {
+                for p in level.pixels() {
+                    assert_eq!([200, 100, 50, 255], p.data);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_content_is_averaged_in_linear_space() {
+        let mut base = image::RgbaImage::from_pixel(2, 1, image::Rgba([0, 0, 0, 255]));
+        base.put_pixel(1, 0, image::Rgba([255, 255, 255, 255]));
+
+        let linear = MipChain::generate(&base, &MipmapOptions {
+            filter: MipmapFilter::Box,
+            color_space: ColorSpace::Linear,
+            alpha_cutoff: None,
+        });
+        let srgb = MipChain::generate(&base, &MipmapOptions {
+            filter: MipmapFilter::Box,
+            color_space: ColorSpace::Srgb,
+            alpha_cutoff: None,
+        });
+
+        assert_eq!(128, linear.levels()[1].get_pixel(0, 0).data[0]);
+        // Half intensity in linear space encodes to 188 in sRGB.
+        assert_eq!(188, srgb.levels()[1].get_pixel(0, 0).data[0]);
+    }
+
+    #[test]
+    fn transparent_texels_do_not_bleed_color() {
+        let mut base = image::RgbaImage::from_pixel(2, 1, image::Rgba([255, 0, 0, 255]));
+        base.put_pixel(1, 0, image::Rgba([0, 255, 0, 0]));
+
+        for &filter in &[MipmapFilter::Box, MipmapFilter::Kaiser, MipmapFilter::Lanczos] {
+            let chain = MipChain::generate(&base, &MipmapOptions {
+                filter,
+                color_space: ColorSpace::Srgb,
+                alpha_cutoff: None,
+            });
+            let p = chain.levels()[1].get_pixel(0, 0).data;
+            assert_eq!([255, 0, 0], [p[0], p[1], p[2]]);
+        }
+    }
+
+    #[test]
+    fn alpha_coverage_is_preserved() {
+        // A radial alpha gradient, so that every level has a spread of alpha
+        // values around the cutoff.
+        let base = image::RgbaImage::from_fn(64, 64, |x, y| {
+            let dx = x as f32 - 31.5;
+            let dy = y as f32 - 31.5;
+            let alpha = 1.0 - (dx * dx + dy * dy).sqrt() / 32.0;
+            image::Rgba([255, 255, 255, ColorSpace::Linear.encode(alpha)])
+        });
+        for &filter in &[MipmapFilter::Box, MipmapFilter::Kaiser, MipmapFilter::Lanczos] {
+            let options = MipmapOptions {
+                filter,
+                color_space: ColorSpace::Linear,
+                alpha_cutoff: Some(0.5),
+            };
+            let chain = MipChain::generate(&base, &options);
+            let base_coverage =
+                LinearImage::decode(&chain.levels()[0], ColorSpace::Linear).coverage(0.5, 1.0);
+            // Down to 8x8, where a single texel changes the coverage by 1/64.
+            // Coverage is matched to the closest whole number of texels.
+            for level in &chain.levels()[1..4] {
+                let coverage = LinearImage::decode(level, ColorSpace::Linear).coverage(0.5, 1.0);
+                assert!(
+                    (coverage - base_coverage).abs() <= 0.02,
+                    "{:?}: coverage {} differs from the base coverage {}",
+                    filter,
+                    coverage,
+                    base_coverage
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+extern crate gl;
+
+use gl::types::*;
+use renderbuffer::{RenderbufferFormatKind, RenderbufferInternalFormat};
+
+fn get_integer(pname: GLenum) -> GLsizei {
+    let mut value: GLint = 0;
+    unsafe {
+        gl::GetIntegerv(pname, &mut value);
+    }
+    value
+}
+
+/// Queries `GL_MAX_SAMPLES`, the largest sample count supported for
+/// multisampled renderbuffers. Multisampled textures have separate limits,
+/// see `max_texture_samples`.
+#[inline]
+pub fn max_samples() -> GLsizei {
+    get_integer(gl::MAX_SAMPLES)
+}
+
+/// The limit that applies to multisampled textures of `internal_format`:
+/// `GL_MAX_DEPTH_TEXTURE_SAMPLES` for depth and stencil formats,
+/// `GL_MAX_INTEGER_SAMPLES` for integer color formats and
+/// `GL_MAX_COLOR_TEXTURE_SAMPLES` for all other formats.
+fn texture_samples_limit(internal_format: GLenum) -> GLenum {
+    match RenderbufferInternalFormat::from_enum(internal_format) {
+        Some(format) => match format.kind() {
+            RenderbufferFormatKind::Color if format.is_integer() => gl::MAX_INTEGER_SAMPLES,
+            RenderbufferFormatKind::Color => gl::MAX_COLOR_TEXTURE_SAMPLES,
+            _ => gl::MAX_DEPTH_TEXTURE_SAMPLES,
+        },
+        None => match internal_format {
+            gl::DEPTH_COMPONENT | gl::DEPTH_COMPONENT32 | gl::DEPTH_STENCIL => {
+                gl::MAX_DEPTH_TEXTURE_SAMPLES
+            }
+            _ => gl::MAX_COLOR_TEXTURE_SAMPLES,
+        },
+    }
+}
+
+/// Queries the largest sample count supported for multisampled textures of
+/// `internal_format`.
+#[inline]
+pub fn max_texture_samples(internal_format: GLenum) -> GLsizei {
+    get_integer(texture_samples_limit(internal_format))
+}
+
+/// A number of samples per pixel that is known to be supported by the
+/// implementation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SampleCount(GLsizei);
+
+impl SampleCount {
+    /// Validates the sample count against `GL_MAX_SAMPLES`. Multisampled
+    /// textures check the count against their own limits when allocated.
+    pub fn new(samples: u32) -> Result<Self, String> {
+        check_samples(samples, max_samples()).map(SampleCount)
+    }
+
+    /// The largest supported sample count.
+    #[inline]
+    pub fn max() -> Self {
+        SampleCount(::std::cmp::max(1, max_samples()))
+    }
+
+    #[inline]
+    pub fn get(&self) -> GLsizei {
+        self.0
+    }
+
+    /// Checks that multisampled textures of `internal_format` support this
+    /// many samples.
+    pub fn check_texture(&self, internal_format: GLenum) -> Result<(), String> {
+        let max = max_texture_samples(internal_format);
+        if self.0 > max {
+            Err(format!(
+                "Textures of format 0x{:X} support at most {} samples, not {}.",
+                internal_format, max, self.0
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Checks `samples` against `max` before converting it, so that counts
+/// above `i32::MAX` do not wrap around.
+fn check_samples(samples: u32, max: GLsizei) -> Result<GLsizei, String> {
+    if samples == 0 {
+        Err(String::from("The sample count must be at least 1."))
+    } else if samples as i64 > max as i64 {
+        Err(format!(
+            "The sample count {} exceeds GL_MAX_SAMPLES ({}).",
+            samples, max
+        ))
+    } else {
+        Ok(samples as GLsizei)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_samples, texture_samples_limit};
+
+    #[test]
+    fn texture_sample_limits_depend_on_the_format() {
+        assert_eq!(gl::MAX_COLOR_TEXTURE_SAMPLES, texture_samples_limit(gl::RGBA8));
+        assert_eq!(gl::MAX_COLOR_TEXTURE_SAMPLES, texture_samples_limit(gl::RGBA16F));
+        assert_eq!(gl::MAX_INTEGER_SAMPLES, texture_samples_limit(gl::RGBA32UI));
+        assert_eq!(gl::MAX_DEPTH_TEXTURE_SAMPLES, texture_samples_limit(gl::DEPTH24_STENCIL8));
+        assert_eq!(gl::MAX_DEPTH_TEXTURE_SAMPLES, texture_samples_limit(gl::DEPTH_COMPONENT));
+    }
+
+    #[test]
+    fn sample_counts_are_checked_before_converting() {
+        assert_eq!(Ok(4), check_samples(4, 8));
+        assert!(check_samples(0, 8).is_err());
+        assert!(check_samples(16, 8).is_err());
+        // Would wrap to a negative count when converted first.
+        assert!(check_samples(u32::max_value(), 8).is_err());
+        assert!(check_samples(0x8000_0000, 8).is_err());
+    }
+}
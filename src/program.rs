@@ -101,9 +101,14 @@ impl<'p> UniformLocation<'p> {
 }
 
 #[derive(Debug)]
-pub struct ProgramSlot();
+pub struct ProgramSlot(());
 
 impl ProgramSlot {
+    #[inline]
+    pub(crate) unsafe fn new() -> Self {
+        ProgramSlot(())
+    }
+
     pub fn bind<'s, 'p>(&'s mut self, program: &'p LinkedProgramId) -> BoundProgramId {
         unsafe {
             gl::UseProgram(program.as_uint());
@@ -0,0 +1,592 @@
+extern crate gl;
+
+use gl::types::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CompareFunc {
+    Never = gl::NEVER,
+    Less = gl::LESS,
+    Equal = gl::EQUAL,
+    LessEqual = gl::LEQUAL,
+    Greater = gl::GREATER,
+    NotEqual = gl::NOTEQUAL,
+    GreaterEqual = gl::GEQUAL,
+    Always = gl::ALWAYS,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DepthState {
+    pub func: CompareFunc,
+    /// Whether fragments that pass the depth test write their depth.
+    pub write: bool,
+}
+
+impl Default for DepthState {
+    #[inline]
+    fn default() -> Self {
+        DepthState {
+            func: CompareFunc::Less,
+            write: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum BlendEquation {
+    Add = gl::FUNC_ADD,
+    Subtract = gl::FUNC_SUBTRACT,
+    ReverseSubtract = gl::FUNC_REVERSE_SUBTRACT,
+    Min = gl::MIN,
+    Max = gl::MAX,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum BlendFactor {
+    Zero = gl::ZERO,
+    One = gl::ONE,
+    SrcColor = gl::SRC_COLOR,
+    OneMinusSrcColor = gl::ONE_MINUS_SRC_COLOR,
+    DstColor = gl::DST_COLOR,
+    OneMinusDstColor = gl::ONE_MINUS_DST_COLOR,
+    SrcAlpha = gl::SRC_ALPHA,
+    OneMinusSrcAlpha = gl::ONE_MINUS_SRC_ALPHA,
+    DstAlpha = gl::DST_ALPHA,
+    OneMinusDstAlpha = gl::ONE_MINUS_DST_ALPHA,
+    ConstantColor = gl::CONSTANT_COLOR,
+    OneMinusConstantColor = gl::ONE_MINUS_CONSTANT_COLOR,
+    ConstantAlpha = gl::CONSTANT_ALPHA,
+    OneMinusConstantAlpha = gl::ONE_MINUS_CONSTANT_ALPHA,
+    SrcAlphaSaturate = gl::SRC_ALPHA_SATURATE,
+}
+
+/// The blend equations and factors for the color and alpha components.
+/// `constant` is the color used by the constant factors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendState {
+    pub color_equation: BlendEquation,
+    pub color_src: BlendFactor,
+    pub color_dst: BlendFactor,
+    pub alpha_equation: BlendEquation,
+    pub alpha_src: BlendFactor,
+    pub alpha_dst: BlendFactor,
+    pub constant: [GLfloat; 4],
+}
+
+impl BlendState {
+    /// Blends with the same equation and factors for all components.
+    #[inline]
+    pub fn new(equation: BlendEquation, src: BlendFactor, dst: BlendFactor) -> Self {
+        BlendState {
+            color_equation: equation,
+            color_src: src,
+            color_dst: dst,
+            alpha_equation: equation,
+            alpha_src: src,
+            alpha_dst: dst,
+            constant: [0.0; 4],
+        }
+    }
+
+    /// Conventional transparency with non-premultiplied alpha.
+    #[inline]
+    pub fn alpha() -> Self {
+        BlendState::new(
+            BlendEquation::Add,
+            BlendFactor::SrcAlpha,
+            BlendFactor::OneMinusSrcAlpha,
+        )
+    }
+
+    /// Transparency with premultiplied alpha.
+    #[inline]
+    pub fn premultiplied_alpha() -> Self {
+        BlendState::new(
+            BlendEquation::Add,
+            BlendFactor::One,
+            BlendFactor::OneMinusSrcAlpha,
+        )
+    }
+
+    #[inline]
+    pub fn additive() -> Self {
+        BlendState::new(BlendEquation::Add, BlendFactor::One, BlendFactor::One)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CullFace {
+    Front = gl::FRONT,
+    Back = gl::BACK,
+    FrontAndBack = gl::FRONT_AND_BACK,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum FrontFace {
+    CounterClockwise = gl::CCW,
+    Clockwise = gl::CW,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CullState {
+    pub face: CullFace,
+    pub front_face: FrontFace,
+}
+
+impl Default for CullState {
+    #[inline]
+    fn default() -> Self {
+        CullState {
+            face: CullFace::Back,
+            front_face: FrontFace::CounterClockwise,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum PolygonMode {
+    Point = gl::POINT,
+    Line = gl::LINE,
+    Fill = gl::FILL,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum StencilOp {
+    Keep = gl::KEEP,
+    Zero = gl::ZERO,
+    Replace = gl::REPLACE,
+    Increment = gl::INCR,
+    IncrementWrap = gl::INCR_WRAP,
+    Decrement = gl::DECR,
+    DecrementWrap = gl::DECR_WRAP,
+    Invert = gl::INVERT,
+}
+
+/// The stencil test and operations for one face.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StencilFaceState {
+    pub func: CompareFunc,
+    pub reference: GLint,
+    pub read_mask: GLuint,
+    pub write_mask: GLuint,
+    /// Performed when the stencil test fails.
+    pub stencil_fail: StencilOp,
+    /// Performed when the stencil test passes and the depth test fails.
+    pub depth_fail: StencilOp,
+    /// Performed when both tests pass.
+    pub pass: StencilOp,
+}
+
+impl Default for StencilFaceState {
+    #[inline]
+    fn default() -> Self {
+        StencilFaceState {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+impl StencilFaceState {
+    #[inline]
+    fn with_default_write_mask(&self) -> Self {
+        StencilFaceState {
+            write_mask: StencilFaceState::default().write_mask,
+            ..*self
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StencilState {
+    pub front: StencilFaceState,
+    pub back: StencilFaceState,
+}
+
+impl StencilState {
+    /// Uses the same state for front and back faces.
+    #[inline]
+    pub fn both(face: StencilFaceState) -> Self {
+        StencilState {
+            front: face,
+            back: face,
+        }
+    }
+}
+
+/// The fixed function state used by draw calls. Tests and blending that
+/// are `None` are disabled. The default matches the initial OpenGL state.
+///
+/// Note that the depth, stencil and color write masks also apply to
+/// clears. Disabling the depth or stencil test resets its write mask to the
+/// default, so that clears after a state without the test write everything.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderState {
+    pub depth: Option<DepthState>,
+    pub blend: Option<BlendState>,
+    pub cull: Option<CullState>,
+    pub polygon_mode: PolygonMode,
+    pub stencil: Option<StencilState>,
+    /// Whether red, green, blue and alpha are written.
+    pub color_mask: [bool; 4],
+}
+
+impl Default for RenderState {
+    #[inline]
+    fn default() -> Self {
+        RenderState {
+            depth: None,
+            blend: None,
+            cull: None,
+            polygon_mode: PolygonMode::Fill,
+            stencil: None,
+            color_mask: [true; 4],
+        }
+    }
+}
+
+/// The OpenGL state that a `RenderState` maps to. Unlike `RenderState`, the
+/// parameters of disabled tests are kept since OpenGL retains them. The
+/// depth and stencil write masks are the exception, they are reset because
+/// they also apply to clears.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct GlState {
+    depth_test: bool,
+    depth: DepthState,
+    blend: bool,
+    blend_state: BlendState,
+    cull_face: bool,
+    cull: CullState,
+    polygon_mode: PolygonMode,
+    stencil_test: bool,
+    stencil: StencilState,
+    color_mask: [bool; 4],
+}
+
+impl GlState {
+    /// The initial OpenGL state.
+    fn initial() -> Self {
+        GlState {
+            depth_test: false,
+            depth: DepthState::default(),
+            blend: false,
+            blend_state: BlendState::new(BlendEquation::Add, BlendFactor::One, BlendFactor::Zero),
+            cull_face: false,
+            cull: CullState::default(),
+            polygon_mode: PolygonMode::Fill,
+            stencil_test: false,
+            stencil: StencilState::default(),
+            color_mask: [true; 4],
+        }
+    }
+
+    /// The state after applying `state` to `self`.
+    fn with(&self, state: &RenderState) -> Self {
+        GlState {
+            depth_test: state.depth.is_some(),
+            depth: state.depth.unwrap_or(DepthState {
+                write: DepthState::default().write,
+                ..self.depth
+            }),
+            blend: state.blend.is_some(),
+            blend_state: state.blend.unwrap_or(self.blend_state),
+            cull_face: state.cull.is_some(),
+            cull: state.cull.unwrap_or(self.cull),
+            polygon_mode: state.polygon_mode,
+            stencil_test: state.stencil.is_some(),
+            stencil: state.stencil.unwrap_or(StencilState {
+                front: self.stencil.front.with_default_write_mask(),
+                back: self.stencil.back.with_default_write_mask(),
+            }),
+            color_mask: state.color_mask,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    Enable(GLenum, bool),
+    DepthFunc(CompareFunc),
+    DepthMask(bool),
+    BlendEquation(BlendEquation, BlendEquation),
+    BlendFunc(BlendFactor, BlendFactor, BlendFactor, BlendFactor),
+    BlendColor([GLfloat; 4]),
+    CullFace(CullFace),
+    FrontFace(FrontFace),
+    PolygonMode(PolygonMode),
+    StencilFunc(GLenum, CompareFunc, GLint, GLuint),
+    StencilOp(GLenum, StencilOp, StencilOp, StencilOp),
+    StencilMask(GLenum, GLuint),
+    ColorMask([bool; 4]),
+}
+
+/// Computes the commands that change `from` into `to`. Every command is
+/// issued when `from` is unknown.
+fn diff(from: Option<&GlState>, to: &GlState) -> Vec<Command> {
+    let mut commands = Vec::new();
+
+    macro_rules! changed {
+        ($($field:ident).+) => {
+            from.map_or(true, |from| from.$($field).+ != to.$($field).+)
+        }
+    }
+
+    if changed!(depth_test) {
+        commands.push(Command::Enable(gl::DEPTH_TEST, to.depth_test));
+    }
+    if changed!(depth.func) {
+        commands.push(Command::DepthFunc(to.depth.func));
+    }
+    if changed!(depth.write) {
+        commands.push(Command::DepthMask(to.depth.write));
+    }
+
+    if changed!(blend) {
+        commands.push(Command::Enable(gl::BLEND, to.blend));
+    }
+    let b = &to.blend_state;
+    if changed!(blend_state.color_equation) || changed!(blend_state.alpha_equation) {
+        commands.push(Command::BlendEquation(b.color_equation, b.alpha_equation));
+    }
+    if changed!(blend_state.color_src) || changed!(blend_state.color_dst)
+        || changed!(blend_state.alpha_src) || changed!(blend_state.alpha_dst)
+    {
+        commands.push(Command::BlendFunc(b.color_src, b.color_dst, b.alpha_src, b.alpha_dst));
+    }
+    if changed!(blend_state.constant) {
+        commands.push(Command::BlendColor(b.constant));
+    }
+
+    if changed!(cull_face) {
+        commands.push(Command::Enable(gl::CULL_FACE, to.cull_face));
+    }
+    if changed!(cull.face) {
+        commands.push(Command::CullFace(to.cull.face));
+    }
+    if changed!(cull.front_face) {
+        commands.push(Command::FrontFace(to.cull.front_face));
+    }
+
+    if changed!(polygon_mode) {
+        commands.push(Command::PolygonMode(to.polygon_mode));
+    }
+
+    if changed!(stencil_test) {
+        commands.push(Command::Enable(gl::STENCIL_TEST, to.stencil_test));
+    }
+    for &(face, to_face, from_face) in &[
+        (gl::FRONT, &to.stencil.front, from.map(|from| &from.stencil.front)),
+        (gl::BACK, &to.stencil.back, from.map(|from| &from.stencil.back)),
+    ] {
+        let func = (to_face.func, to_face.reference, to_face.read_mask);
+        if from_face.map_or(true, |f| (f.func, f.reference, f.read_mask) != func) {
+            commands.push(Command::StencilFunc(face, func.0, func.1, func.2));
+        }
+        let ops = (to_face.stencil_fail, to_face.depth_fail, to_face.pass);
+        if from_face.map_or(true, |f| (f.stencil_fail, f.depth_fail, f.pass) != ops) {
+            commands.push(Command::StencilOp(face, ops.0, ops.1, ops.2));
+        }
+        if from_face.map_or(true, |f| f.write_mask != to_face.write_mask) {
+            commands.push(Command::StencilMask(face, to_face.write_mask));
+        }
+    }
+
+    if changed!(color_mask) {
+        commands.push(Command::ColorMask(to.color_mask));
+    }
+
+    commands
+}
+
+#[inline]
+fn gl_bool(value: bool) -> GLboolean {
+    if value {
+        gl::TRUE
+    } else {
+        gl::FALSE
+    }
+}
+
+unsafe fn execute(command: &Command) {
+    match *command {
+        Command::Enable(cap, true) => gl::Enable(cap),
+        Command::Enable(cap, false) => gl::Disable(cap),
+        Command::DepthFunc(func) => gl::DepthFunc(func as GLenum),
+        Command::DepthMask(write) => gl::DepthMask(gl_bool(write)),
+        Command::BlendEquation(color, alpha) => {
+            gl::BlendEquationSeparate(color as GLenum, alpha as GLenum)
+        }
+        Command::BlendFunc(color_src, color_dst, alpha_src, alpha_dst) => gl::BlendFuncSeparate(
+            color_src as GLenum,
+            color_dst as GLenum,
+            alpha_src as GLenum,
+            alpha_dst as GLenum,
+        ),
+        Command::BlendColor([r, g, b, a]) => gl::BlendColor(r, g, b, a),
+        Command::CullFace(face) => gl::CullFace(face as GLenum),
+        Command::FrontFace(front_face) => gl::FrontFace(front_face as GLenum),
+        Command::PolygonMode(mode) => gl::PolygonMode(gl::FRONT_AND_BACK, mode as GLenum),
+        Command::StencilFunc(face, func, reference, mask) => {
+            gl::StencilFuncSeparate(face, func as GLenum, reference, mask)
+        }
+        Command::StencilOp(face, stencil_fail, depth_fail, pass) => gl::StencilOpSeparate(
+            face,
+            stencil_fail as GLenum,
+            depth_fail as GLenum,
+            pass as GLenum,
+        ),
+        Command::StencilMask(face, mask) => gl::StencilMaskSeparate(face, mask),
+        Command::ColorMask([r, g, b, a]) => {
+            gl::ColorMask(gl_bool(r), gl_bool(g), gl_bool(b), gl_bool(a))
+        }
+    }
+}
+
+/// Applies `RenderState`s, only issuing the GL calls for state that differs
+/// from the last applied state.
+pub struct RenderStateApplier {
+    current: Option<GlState>,
+}
+
+impl RenderStateApplier {
+    /// Assumes the initial OpenGL state, so create it right after the
+    /// context.
+    #[inline]
+    pub fn new() -> Self {
+        RenderStateApplier {
+            current: Some(GlState::initial()),
+        }
+    }
+
+    /// Forgets the known state so the next `apply` issues every call. Use
+    /// this after changing state through raw GL calls.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.current = None;
+    }
+
+    pub fn apply(&mut self, state: &RenderState) {
+        let next = self.current
+            .unwrap_or_else(GlState::initial)
+            .with(state);
+        for command in diff(self.current.as_ref(), &next).iter() {
+            unsafe {
+                execute(command);
+            }
+        }
+        self.current = Some(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_state_issues_nothing() {
+        let initial = GlState::initial();
+        assert_eq!(
+            Vec::<Command>::new(),
+            diff(Some(&initial), &initial.with(&RenderState::default()))
+        );
+    }
+
+    #[test]
+    fn only_changes_are_issued() {
+        let initial = GlState::initial();
+        let depth_tested = initial.with(&RenderState {
+            depth: Some(DepthState::default()),
+            ..RenderState::default()
+        });
+        assert_eq!(
+            vec![Command::Enable(gl::DEPTH_TEST, true)],
+            diff(Some(&initial), &depth_tested)
+        );
+
+        let blended = depth_tested.with(&RenderState {
+            depth: Some(DepthState {
+                func: CompareFunc::LessEqual,
+                write: false,
+            }),
+            blend: Some(BlendState::alpha()),
+            ..RenderState::default()
+        });
+        assert_eq!(
+            vec![
+                Command::DepthFunc(CompareFunc::LessEqual),
+                Command::DepthMask(false),
+                Command::Enable(gl::BLEND, true),
+                Command::BlendFunc(
+                    BlendFactor::SrcAlpha,
+                    BlendFactor::OneMinusSrcAlpha,
+                    BlendFactor::SrcAlpha,
+                    BlendFactor::OneMinusSrcAlpha,
+                ),
+            ],
+            diff(Some(&depth_tested), &blended)
+        );
+    }
+
+    #[test]
+    fn disabling_keeps_parameters() {
+        let initial = GlState::initial();
+        let culled = initial.with(&RenderState {
+            cull: Some(CullState {
+                face: CullFace::Front,
+                front_face: FrontFace::CounterClockwise,
+            }),
+            ..RenderState::default()
+        });
+        let unculled = culled.with(&RenderState::default());
+        assert_eq!(CullFace::Front, unculled.cull.face);
+        assert_eq!(
+            vec![Command::Enable(gl::CULL_FACE, false)],
+            diff(Some(&culled), &unculled)
+        );
+    }
+
+    #[test]
+    fn disabling_resets_write_masks() {
+        let initial = GlState::initial();
+        let masked = initial.with(&RenderState {
+            depth: Some(DepthState {
+                func: CompareFunc::LessEqual,
+                write: false,
+            }),
+            stencil: Some(StencilState::both(StencilFaceState {
+                write_mask: 0,
+                ..StencilFaceState::default()
+            })),
+            ..RenderState::default()
+        });
+        let unmasked = masked.with(&RenderState::default());
+        assert_eq!(CompareFunc::LessEqual, unmasked.depth.func);
+        assert_eq!(
+            vec![
+                Command::Enable(gl::DEPTH_TEST, false),
+                Command::DepthMask(true),
+                Command::Enable(gl::STENCIL_TEST, false),
+                Command::StencilMask(gl::FRONT, !0),
+                Command::StencilMask(gl::BACK, !0),
+            ],
+            diff(Some(&masked), &unmasked)
+        );
+    }
+
+    #[test]
+    fn unknown_state_issues_everything() {
+        let initial = GlState::initial();
+        assert_eq!(19, diff(None, &initial).len());
+    }
+}
@@ -0,0 +1,406 @@
+extern crate gl;
+
+use gl::types::*;
+use framebuffer::*;
+use multisample::SampleCount;
+use context::GlContext;
+use renderbuffer::{RenderbufferId, RenderbufferInternalFormat};
+use texture::{TextureFilter, TextureId, TextureUnit};
+use viewport::Viewport;
+
+/// The size of a render target relative to the viewport.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RenderTargetSize {
+    /// The size of the viewport.
+    Full,
+    /// The size of the viewport divided by the given positive factor,
+    /// rounded up.
+    Divided(GLsizei),
+    /// A size independent of the viewport.
+    Fixed(GLsizei, GLsizei),
+}
+
+impl RenderTargetSize {
+    pub fn resolve(&self, viewport: &Viewport) -> (GLsizei, GLsizei) {
+        match *self {
+            RenderTargetSize::Full => (viewport.width(), viewport.height()),
+            RenderTargetSize::Divided(factor) => (
+                (viewport.width() + factor - 1) / factor,
+                (viewport.height() + factor - 1) / factor,
+            ),
+            RenderTargetSize::Fixed(width, height) => (width, height),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        match *self {
+            RenderTargetSize::Divided(factor) if factor <= 0 => Err(format!(
+                "Render target size divisor {} is not positive.",
+                factor
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The parameters passed to `glTexImage2D` when allocating a texture
+/// attachment.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TextureStorage {
+    pub internal_format: GLenum,
+    pub format: GLenum,
+    pub component_format: GLenum,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AttachmentStorage {
+    /// A texture, for attachments that are sampled afterwards. Textures are
+    /// created with nearest filtering and clamp to edge wrapping.
+    Texture(TextureStorage),
+    /// A renderbuffer, for attachments that are only rendered to, blitted
+    /// or read back.
+    Renderbuffer(RenderbufferInternalFormat),
+}
+
+enum AttachmentObject {
+    Texture(TextureId),
+    Renderbuffer(RenderbufferId),
+}
+
+/// Checks that every attachment point is declared once and that the color
+/// attachments can all be drawn to at the same time.
+fn check_attachments(
+    attachments: &[(FramebufferAttachment, AttachmentStorage)],
+    max_color_attachments: usize,
+    max_draw_buffers: usize,
+) -> Result<(), String> {
+    let mut color_count = 0;
+    for (index, &(attachment, _)) in attachments.iter().enumerate() {
+        if attachments[..index].iter().any(|&(a, _)| a == attachment) {
+            return Err(format!("{:?} is declared more than once.", attachment));
+        }
+        if let Some(color_index) = attachment.color_index() {
+            if color_index as usize >= max_color_attachments {
+                return Err(format!(
+                    "{:?} exceeds GL_MAX_COLOR_ATTACHMENTS ({}).",
+                    attachment, max_color_attachments
+                ));
+            }
+            color_count += 1;
+        }
+    }
+    if color_count > max_draw_buffers {
+        return Err(format!(
+            "{} color attachments exceed GL_MAX_DRAW_BUFFERS ({}).",
+            color_count, max_draw_buffers
+        ));
+    }
+    Ok(())
+}
+
+/// The reason a render target could not be created or updated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RenderTargetError {
+    /// The size is invalid.
+    Size(String),
+    /// The attachments are declared more than once or do not fit in the
+    /// limits of the context.
+    Attachments(String),
+    /// A framebuffer, texture or renderbuffer name could not be acquired.
+    Name(String),
+    /// Storage for the given attachment point could not be allocated.
+    Storage(FramebufferAttachment, String),
+    /// An image could not be attached at the given attachment point.
+    Attachment(FramebufferAttachment, AttachmentError),
+    /// The color attachments could not be selected as draw buffers.
+    DrawBuffers(String),
+    /// The framebuffer is incomplete after reallocating the attachments.
+    Incomplete(FramebufferIncomplete),
+}
+
+impl ::std::fmt::Display for RenderTargetError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            RenderTargetError::Size(ref message)
+            | RenderTargetError::Attachments(ref message)
+            | RenderTargetError::Name(ref message) => write!(f, "{}", message),
+            RenderTargetError::Storage(attachment, ref message) => {
+                write!(f, "failed to allocate {:?}: {}", attachment, message)
+            }
+            RenderTargetError::Attachment(attachment, ref error) => {
+                write!(f, "failed to attach {:?}: {}", attachment, error)
+            }
+            RenderTargetError::DrawBuffers(ref message) => write!(f, "{}", message),
+            RenderTargetError::Incomplete(ref incomplete) => {
+                write!(f, "the framebuffer is incomplete: {}", incomplete)
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for RenderTargetError {}
+
+impl From<FramebufferIncomplete> for RenderTargetError {
+    fn from(incomplete: FramebufferIncomplete) -> Self {
+        RenderTargetError::Incomplete(incomplete)
+    }
+}
+
+struct RenderTargetAttachment {
+    attachment: FramebufferAttachment,
+    storage: AttachmentStorage,
+    object: AttachmentObject,
+}
+
+/// A framebuffer together with the images attached to it, sized relative
+/// to the viewport. Call `update` whenever the viewport changes.
+///
+/// The color attachments become the draw buffers in the order they were
+/// declared, so fragment output location `i` is written to the `i`th
+/// declared color attachment.
+pub struct RenderTarget {
+    framebuffer_id: FramebufferId,
+    size: RenderTargetSize,
+    samples: Option<SampleCount>,
+    attachments: Vec<RenderTargetAttachment>,
+    width: GLsizei,
+    height: GLsizei,
+}
+
+impl RenderTarget {
+    /// Acquires the framebuffer and attachment names. No storage is
+    /// allocated until the first call to `update`. Every attachment point
+    /// may be declared once and the color attachments must fit in the
+    /// draw buffers of the context.
+    pub fn new(
+        context: &GlContext,
+        size: RenderTargetSize,
+        samples: Option<SampleCount>,
+        attachments: &[(FramebufferAttachment, AttachmentStorage)],
+    ) -> Result<Self, RenderTargetError> {
+        size.check().map_err(RenderTargetError::Size)?;
+        let limits = context.limits();
+        check_attachments(
+            attachments,
+            limits.max_color_attachments as usize,
+            limits.max_draw_buffers as usize,
+        ).map_err(RenderTargetError::Attachments)?;
+
+        let framebuffer_id = FramebufferId::new().ok_or_else(|| {
+            RenderTargetError::Name(String::from("Failed to acquire framebuffer id."))
+        })?;
+
+        let attachments = attachments
+            .iter()
+            .map(|&(attachment, storage)| {
+                let object = match storage {
+                    AttachmentStorage::Texture(_) => {
+                        AttachmentObject::Texture(TextureId::new().ok_or_else(|| {
+                            RenderTargetError::Name(String::from("Failed to acquire texture id."))
+                        })?)
+                    }
+                    AttachmentStorage::Renderbuffer(_) => {
+                        AttachmentObject::Renderbuffer(RenderbufferId::new().ok_or_else(|| {
+                            RenderTargetError::Name(String::from(
+                                "Failed to acquire renderbuffer id.",
+                            ))
+                        })?)
+                    }
+                };
+                Ok(RenderTargetAttachment {
+                    attachment,
+                    storage,
+                    object,
+                })
+            })
+            .collect::<Result<Vec<_>, RenderTargetError>>()?;
+
+        Ok(RenderTarget {
+            framebuffer_id,
+            size,
+            samples,
+            attachments,
+            width: 0,
+            height: 0,
+        })
+    }
+
+    #[inline]
+    pub fn framebuffer_id(&self) -> &FramebufferId {
+        &self.framebuffer_id
+    }
+
+    #[inline]
+    pub fn width(&self) -> GLsizei {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> GLsizei {
+        self.height
+    }
+
+    /// The texture attached at `attachment`, if it is backed by a texture.
+    pub fn texture(&self, attachment: FramebufferAttachment) -> Option<&TextureId> {
+        self.attachments
+            .iter()
+            .filter(|a| a.attachment == attachment)
+            .filter_map(|a| match a.object {
+                AttachmentObject::Texture(ref texture_id) => Some(texture_id),
+                AttachmentObject::Renderbuffer(_) => None,
+            })
+            .next()
+    }
+
+    /// Reallocates all attachments when the size derived from the viewport
+    /// changed and checks the framebuffer for completeness. Returns whether
+    /// anything was reallocated.
+    ///
+    /// Sizes with a zero dimension, like those of a minimized window, keep
+    /// the current storage. Texture unit 0 and the renderbuffer slot are used
+    /// to allocate the storage and are left with the last attachment bound.
+    pub fn update(
+        &mut self,
+        viewport: &Viewport,
+        context: &mut GlContext,
+    ) -> Result<bool, RenderTargetError> {
+        let (width, height) = self.size.resolve(viewport);
+        if width <= 0 || height <= 0 || (width, height) == (self.width, self.height) {
+            return Ok(false);
+        }
+
+        let attach = self.width == 0;
+
+        let mut renderbuffer_target = context.renderbuffer_slot.target();
+        let mut bound_fb = DrawReadFramebufferTarget::new(
+            &mut context.draw_framebuffer_slot,
+            &mut context.read_framebuffer_slot,
+        ).bind(&self.framebuffer_id);
+
+        for a in self.attachments.iter() {
+            match (a.storage, &a.object) {
+                (AttachmentStorage::Texture(storage), &AttachmentObject::Texture(ref texture_id)) => {
+                    let mut unit = context.texture_unit_slot.activate(TextureUnit::TextureUnit0);
+                    match self.samples {
+                        Some(samples) => {
+                            let mut target = unit.texture_slot_2d_multisample.target();
+                            let mut bound_texture = target.bind(texture_id);
+                            bound_texture
+                                .image_2d_multisample(
+                                    samples,
+                                    storage.internal_format,
+                                    width,
+                                    height,
+                                    true,
+                                )
+                                .map_err(|e| RenderTargetError::Storage(a.attachment, e))?;
+                            if attach {
+                                bound_fb
+                                    .attach_texture_2d(a.attachment, &bound_texture, 0)
+                                    .map_err(|e| RenderTargetError::Attachment(a.attachment, e))?;
+                            }
+                        }
+                        None => {
+                            let mut target = unit.texture_slot_2d.target();
+                            let mut bound_texture = target.bind(texture_id);
+                            unsafe {
+                                bound_texture
+                                    .min_filter(TextureFilter::Nearest)
+                                    .mag_filter(TextureFilter::Nearest)
+                                    .wrap_s(gl::CLAMP_TO_EDGE as GLint)
+                                    .wrap_t(gl::CLAMP_TO_EDGE as GLint)
+                                    .image_2d(
+                                        0,
+                                        storage.internal_format as GLint,
+                                        width,
+                                        height,
+                                        storage.format,
+                                        storage.component_format,
+                                        ::std::ptr::null(),
+                                    );
+                            }
+                            if attach {
+                                bound_fb
+                                    .attach_texture_2d(a.attachment, &bound_texture, 0)
+                                    .map_err(|e| RenderTargetError::Attachment(a.attachment, e))?;
+                            }
+                        }
+                    }
+                }
+                (
+                    AttachmentStorage::Renderbuffer(internal_format),
+                    &AttachmentObject::Renderbuffer(ref renderbuffer_id),
+                ) => {
+                    let mut bound_rb = renderbuffer_target.bind(renderbuffer_id);
+                    match self.samples {
+                        Some(samples) => {
+                            bound_rb.storage_multisample(samples, internal_format, width, height);
+                        }
+                        None => {
+                            bound_rb.storage(internal_format, width, height);
+                        }
+                    }
+                    if attach {
+                        bound_fb
+                            .attach_renderbuffer(a.attachment, &bound_rb)
+                            .map_err(|e| RenderTargetError::Attachment(a.attachment, e))?;
+                    }
+                }
+                _ => unreachable!("Attachment object does not match its storage."),
+            }
+        }
+
+        if attach {
+            let draw_buffers: Vec<Option<FramebufferAttachment>> = self.attachments
+                .iter()
+                .filter(|a| a.attachment.color_index().is_some())
+                .map(|a| Some(a.attachment))
+                .collect();
+            bound_fb
+                .draw_buffers(&draw_buffers)
+                .map_err(RenderTargetError::DrawBuffers)?;
+        }
+
+        bound_fb.check_status()?;
+
+        // Only remember the size once the framebuffer is complete, so a
+        // failed update is retried on the next call.
+        self.width = width;
+        self.height = height;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_attachments, AttachmentStorage, RenderTargetSize};
+    use framebuffer::FramebufferAttachment;
+    use renderbuffer::RenderbufferInternalFormat;
+    use viewport::Viewport;
+
+    #[test]
+    fn sizes_resolve_relative_to_the_viewport() {
+        let viewport = Viewport::new(801, 600);
+        assert_eq!(RenderTargetSize::Full.resolve(&viewport), (801, 600));
+        assert_eq!(RenderTargetSize::Divided(2).resolve(&viewport), (401, 300));
+        assert_eq!(RenderTargetSize::Fixed(64, 32).resolve(&viewport), (64, 32));
+    }
+
+    #[test]
+    fn divisors_must_be_positive() {
+        assert!(RenderTargetSize::Divided(2).check().is_ok());
+        assert!(RenderTargetSize::Divided(0).check().is_err());
+        assert!(RenderTargetSize::Divided(-1).check().is_err());
+    }
+
+    #[test]
+    fn attachments_are_declared_once_and_fit_the_draw_buffers() {
+        use self::FramebufferAttachment::*;
+        let color = AttachmentStorage::Renderbuffer(RenderbufferInternalFormat::RGB8);
+        let depth = AttachmentStorage::Renderbuffer(RenderbufferInternalFormat::DEPTH24_STENCIL8);
+        assert!(check_attachments(&[(Color0, color), (DepthStencil, depth)], 8, 8).is_ok());
+        assert!(check_attachments(&[(Color0, color), (Color0, color)], 8, 8).is_err());
+        assert!(check_attachments(&[(Color0, color), (Color1, color)], 8, 1).is_err());
+        assert!(check_attachments(&[(Color4, color)], 4, 8).is_err());
+    }
+}
@@ -7,6 +7,7 @@ use id::Id;
 use gl::types::*;
 use std::marker::PhantomData;
 use phantomdata::into_phantom_data;
+use multisample::SampleCount;
 
 pub struct RenderbufferId(Id);
 
@@ -35,9 +36,14 @@ impl Drop for RenderbufferId {
     }
 }
 
-pub struct RenderbufferSlot;
+pub struct RenderbufferSlot(());
 
 impl RenderbufferSlot {
+    #[inline]
+    pub(crate) unsafe fn new() -> Self {
+        RenderbufferSlot(())
+    }
+
     #[inline]
     pub fn target(&mut self) -> RenderbufferTarget {
         RenderbufferTarget::new(self)
@@ -75,17 +81,56 @@ impl<'s> RenderbufferTarget<'s> {
         }
         BoundRenderbufferId {
             target: self,
-            renderbuffer_id: into_phantom_data(renderbuffer_id),
+            renderbuffer_id,
         }
     }
 }
 
 pub struct BoundRenderbufferId<'s: 't, 't, 'i> {
     target: &'t mut RenderbufferTarget<'s>,
-    renderbuffer_id: PhantomData<&'i RenderbufferId>,
+    renderbuffer_id: &'i RenderbufferId,
 }
 
 impl<'s: 't, 't, 'i> BoundRenderbufferId<'s, 't, 'i> {
+    #[inline]
+    pub fn renderbuffer_id(&self) -> &'i RenderbufferId {
+        self.renderbuffer_id
+    }
+
+    #[inline]
+    fn parameter_i(&self, param: GLenum) -> GLint {
+        let mut value: GLint = 0;
+        unsafe {
+            gl::GetRenderbufferParameteriv(self.target.as_enum(), param, &mut value);
+        }
+        value
+    }
+
+    /// The allocated width, 0 before storage has been allocated.
+    #[inline]
+    pub fn width(&self) -> GLsizei {
+        self.parameter_i(gl::RENDERBUFFER_WIDTH)
+    }
+
+    /// The allocated height, 0 before storage has been allocated.
+    #[inline]
+    pub fn height(&self) -> GLsizei {
+        self.parameter_i(gl::RENDERBUFFER_HEIGHT)
+    }
+
+    /// The allocated number of samples, 0 when not multisampled.
+    #[inline]
+    pub fn samples(&self) -> GLsizei {
+        self.parameter_i(gl::RENDERBUFFER_SAMPLES)
+    }
+
+    /// The allocated internal format. `None` for formats that can not be
+    /// passed to `storage`, like the `GL_RGBA` default.
+    #[inline]
+    pub fn internal_format(&self) -> Option<RenderbufferInternalFormat> {
+        RenderbufferInternalFormat::from_enum(self.parameter_i(gl::RENDERBUFFER_INTERNAL_FORMAT) as GLenum)
+    }
+
     #[inline]
     pub fn storage(
         &mut self,
@@ -103,8 +148,29 @@ impl<'s: 't, 't, 'i> BoundRenderbufferId<'s, 't, 'i> {
         }
         self
     }
+
+    #[inline]
+    pub fn storage_multisample(
+        &mut self,
+        samples: SampleCount,
+        internal_format: RenderbufferInternalFormat,
+        width: GLsizei,
+        height: GLsizei,
+    ) -> &mut Self {
+        unsafe {
+            gl::RenderbufferStorageMultisample(
+                self.target.as_enum(),
+                samples.get(),
+                internal_format as GLenum,
+                width,
+                height,
+            );
+        }
+        self
+    }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
 #[allow(non_camel_case_types)]
 pub enum RenderbufferInternalFormat {
@@ -143,3 +209,145 @@ pub enum RenderbufferInternalFormat {
     DEPTH32F_STENCIL8 = gl::DEPTH32F_STENCIL8,
     STENCIL_INDEX8 = gl::STENCIL_INDEX8,
 }
+
+/// What a renderbuffer format stores, which determines the attachment
+/// points it can be attached to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RenderbufferFormatKind {
+    Color,
+    Depth,
+    Stencil,
+    DepthStencil,
+}
+
+/// All formats, in declaration order.
+const RENDERBUFFER_INTERNAL_FORMATS: [RenderbufferInternalFormat; 34] = [
+    RenderbufferInternalFormat::R8,
+    RenderbufferInternalFormat::R8UI,
+    RenderbufferInternalFormat::R8I,
+    RenderbufferInternalFormat::R16UI,
+    RenderbufferInternalFormat::R16I,
+    RenderbufferInternalFormat::R32UI,
+    RenderbufferInternalFormat::R32I,
+    RenderbufferInternalFormat::RG8,
+    RenderbufferInternalFormat::RG8UI,
+    RenderbufferInternalFormat::RG8I,
+    RenderbufferInternalFormat::RG16UI,
+    RenderbufferInternalFormat::RG16I,
+    RenderbufferInternalFormat::RG32UI,
+    RenderbufferInternalFormat::RG32I,
+    RenderbufferInternalFormat::RGB8,
+    RenderbufferInternalFormat::RGB565,
+    RenderbufferInternalFormat::RGBA8,
+    RenderbufferInternalFormat::SRGB8_ALPHA8,
+    RenderbufferInternalFormat::RGB5_A1,
+    RenderbufferInternalFormat::RGBA4,
+    RenderbufferInternalFormat::RGB10_A2,
+    RenderbufferInternalFormat::RGBA8UI,
+    RenderbufferInternalFormat::RGBA8I,
+    RenderbufferInternalFormat::RGB10_A2UI,
+    RenderbufferInternalFormat::RGBA16UI,
+    RenderbufferInternalFormat::RGBA16I,
+    RenderbufferInternalFormat::RGBA32I,
+    RenderbufferInternalFormat::RGBA32UI,
+    RenderbufferInternalFormat::DEPTH_COMPONENT16,
+    RenderbufferInternalFormat::DEPTH_COMPONENT24,
+    RenderbufferInternalFormat::DEPTH_COMPONENT32F,
+    RenderbufferInternalFormat::DEPTH24_STENCIL8,
+    RenderbufferInternalFormat::DEPTH32F_STENCIL8,
+    RenderbufferInternalFormat::STENCIL_INDEX8,
+];
+
+impl RenderbufferInternalFormat {
+    #[inline]
+    pub fn from_enum(value: GLenum) -> Option<Self> {
+        RENDERBUFFER_INTERNAL_FORMATS
+            .iter()
+            .cloned()
+            .find(|&format| format as GLenum == value)
+    }
+
+    /// The size of a pixel derived from the bit depths of the format.
+    /// Implementations may pad pixels, for example storing `RGB8` in 4
+    /// bytes.
+    pub fn bytes_per_pixel(&self) -> usize {
+        use self::RenderbufferInternalFormat::*;
+        match *self {
+            R8 | R8UI | R8I | STENCIL_INDEX8 => 1,
+            R16UI | R16I | RG8 | RG8UI | RG8I | RGB565 | RGB5_A1 | RGBA4
+            | DEPTH_COMPONENT16 => 2,
+            RGB8 | DEPTH_COMPONENT24 => 3,
+            R32UI | R32I | RG16UI | RG16I | RGBA8 | SRGB8_ALPHA8 | RGB10_A2 | RGBA8UI
+            | RGBA8I | RGB10_A2UI | DEPTH_COMPONENT32F | DEPTH24_STENCIL8 => 4,
+            DEPTH32F_STENCIL8 => 5,
+            RG32UI | RG32I | RGBA16UI | RGBA16I => 8,
+            RGBA32I | RGBA32UI => 16,
+        }
+    }
+
+    pub fn kind(&self) -> RenderbufferFormatKind {
+        use self::RenderbufferInternalFormat::*;
+        match *self {
+            DEPTH_COMPONENT16 | DEPTH_COMPONENT24 | DEPTH_COMPONENT32F => {
+                RenderbufferFormatKind::Depth
+            }
+            DEPTH24_STENCIL8 | DEPTH32F_STENCIL8 => RenderbufferFormatKind::DepthStencil,
+            STENCIL_INDEX8 => RenderbufferFormatKind::Stencil,
+            _ => RenderbufferFormatKind::Color,
+        }
+    }
+
+    /// Whether the color components are signed or unsigned integers, or the
+    /// format only stores stencil indices. Integer color can not be
+    /// blended or filtered.
+    pub fn is_integer(&self) -> bool {
+        use self::RenderbufferInternalFormat::*;
+        match *self {
+            R8UI | R8I | R16UI | R16I | R32UI | R32I | RG8UI | RG8I | RG16UI | RG16I
+            | RG32UI | RG32I | RGBA8UI | RGBA8I | RGB10_A2UI | RGBA16UI | RGBA16I
+            | RGBA32I | RGBA32UI | STENCIL_INDEX8 => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the color or depth components are fixed point values that
+    /// read as floating point values in [0, 1].
+    pub fn is_normalized(&self) -> bool {
+        use self::RenderbufferInternalFormat::*;
+        match *self {
+            R8 | RG8 | RGB8 | RGB565 | RGBA8 | SRGB8_ALPHA8 | RGB5_A1 | RGBA4 | RGB10_A2
+            | DEPTH_COMPONENT16 | DEPTH_COMPONENT24 | DEPTH24_STENCIL8 => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderbufferFormatKind;
+    use super::RenderbufferInternalFormat;
+    use super::RENDERBUFFER_INTERNAL_FORMATS;
+
+    #[test]
+    fn formats_round_trip_through_enums() {
+        for &format in RENDERBUFFER_INTERNAL_FORMATS.iter() {
+            assert_eq!(Some(format), RenderbufferInternalFormat::from_enum(format as u32));
+        }
+        assert_eq!(None, RenderbufferInternalFormat::from_enum(0));
+    }
+
+    #[test]
+    fn formats_are_classified() {
+        use self::RenderbufferInternalFormat::*;
+        assert_eq!(RenderbufferFormatKind::Color, RGBA8.kind());
+        assert_eq!(RenderbufferFormatKind::Depth, DEPTH_COMPONENT24.kind());
+        assert_eq!(RenderbufferFormatKind::DepthStencil, DEPTH24_STENCIL8.kind());
+        assert_eq!(RenderbufferFormatKind::Stencil, STENCIL_INDEX8.kind());
+
+        assert!(RGBA8.is_normalized() && !RGBA8.is_integer());
+        assert!(RGBA32UI.is_integer() && !RGBA32UI.is_normalized());
+        assert!(!DEPTH_COMPONENT32F.is_integer() && !DEPTH_COMPONENT32F.is_normalized());
+        assert_eq!(16, RGBA32UI.bytes_per_pixel());
+        assert_eq!(4, DEPTH24_STENCIL8.bytes_per_pixel());
+    }
+}
@@ -0,0 +1,397 @@
+extern crate gl;
+
+use buffer::{BufferId, BufferTarget};
+use context::GlContext;
+use fence::Fence;
+use gl::types::*;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem;
+use std::ptr;
+
+/// Byte bookkeeping for a ring of `capacity` bytes. Allocations are made at
+/// the head and bytes are released a frame at a time from the tail.
+#[derive(Debug)]
+struct RingAllocator {
+    capacity: usize,
+    head: usize,
+    used: usize,
+    frame_used: usize,
+}
+
+impl RingAllocator {
+    fn new(capacity: usize) -> Self {
+        RingAllocator {
+            capacity,
+            head: 0,
+            used: 0,
+            frame_used: 0,
+        }
+    }
+
+    /// Returns the offset of `size` bytes aligned to `alignment`, or `None`
+    /// when there is not enough free space. Bytes skipped for alignment or
+    /// at the end of the ring count as used until their frame is released.
+    fn allocate(&mut self, size: usize, alignment: usize) -> Option<usize> {
+        let aligned = (self.head + alignment - 1) / alignment * alignment;
+        let (start, skipped) = if aligned + size <= self.capacity {
+            (aligned, aligned - self.head)
+        } else {
+            (0, self.capacity - self.head)
+        };
+
+        let needed = skipped + size;
+        if self.used + needed > self.capacity {
+            return None;
+        }
+
+        self.head = start + size;
+        self.used += needed;
+        self.frame_used += needed;
+        Some(start)
+    }
+
+    /// Closes the current frame and returns the number of bytes it used.
+    fn end_frame(&mut self) -> usize {
+        mem::replace(&mut self.frame_used, 0)
+    }
+
+    /// Makes the bytes of the oldest closed frame available again. An empty
+    /// ring starts over at the front so that no bytes are skipped.
+    fn release(&mut self, bytes: usize) {
+        self.used -= bytes;
+        if self.used == 0 {
+            self.head = 0;
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = RingAllocator::new(self.capacity);
+    }
+}
+
+#[derive(Debug)]
+enum RingStorage {
+    /// Persistently and coherently mapped immutable storage.
+    Persistent(*mut u8),
+    /// Mutable storage that is orphaned when the ring is full.
+    Orphaning,
+}
+
+#[derive(Debug)]
+struct RingState {
+    allocator: RingAllocator,
+    frames: VecDeque<(Fence, usize)>,
+}
+
+/// A region of a `RingBuffer` that holds data written this frame.
+#[derive(Debug)]
+pub struct RingAllocation<'r> {
+    buffer_id: &'r BufferId,
+    offset: usize,
+    size: usize,
+}
+
+impl<'r> RingAllocation<'r> {
+    #[inline]
+    pub fn buffer_id(&self) -> &BufferId {
+        self.buffer_id
+    }
+
+    /// The offset in bytes from the start of the buffer.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The size in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Binds the region to binding point `index` of an indexed target like
+    /// `BufferTarget::UniformBuffer`.
+    pub fn bind_range(&self, target: BufferTarget, index: GLuint) {
+        unsafe {
+            gl::BindBufferRange(
+                target as GLenum,
+                index,
+                self.buffer_id.as_uint(),
+                self.offset as GLintptr,
+                self.size as GLsizeiptr,
+            );
+        }
+    }
+}
+
+/// A ring allocator for data that is written once per frame, like per-frame
+/// uniforms and dynamic geometry.
+///
+/// When buffer storage is available the buffer is mapped persistently and a
+/// fence is inserted at the end of every frame. Regions are only reused once
+/// the fence of the frame that wrote them is signaled, waiting if the ring
+/// is full. Without buffer storage, as on plain GL 3.3, the buffer is
+/// orphaned at the end of every frame instead and written through
+/// unsynchronized mappings. Either way a single frame can not use more than
+/// the capacity of the ring.
+///
+/// Allocations borrow the ring, so they can not outlive the frame they were
+/// made in.
+#[derive(Debug)]
+pub struct RingBuffer {
+    buffer_id: BufferId,
+    capacity: usize,
+    storage: RingStorage,
+    state: RefCell<RingState>,
+}
+
+impl RingBuffer {
+    pub fn new(context: &GlContext, capacity: usize) -> Result<Self, String> {
+        if capacity == 0 {
+            return Err(String::from("Can not create an empty ring buffer."));
+        }
+
+        let buffer_id =
+            BufferId::new().ok_or_else(|| String::from("Failed to acquire buffer id."))?;
+        buffer_id.bind(BufferTarget::CopyWriteBuffer);
+
+        let storage = if context.capabilities().has_buffer_storage() {
+            let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+            let ptr = unsafe {
+                gl::BufferStorage(
+                    gl::COPY_WRITE_BUFFER,
+                    capacity as GLsizeiptr,
+                    ptr::null(),
+                    flags,
+                );
+                gl::MapBufferRange(gl::COPY_WRITE_BUFFER, 0, capacity as GLsizeiptr, flags)
+            };
+            if ptr.is_null() {
+                return Err(String::from("Failed to persistently map ring buffer."));
+            }
+            RingStorage::Persistent(ptr as *mut u8)
+        } else {
+            orphan(capacity);
+            RingStorage::Orphaning
+        };
+
+        Ok(RingBuffer {
+            buffer_id,
+            capacity,
+            storage,
+            state: RefCell::new(RingState {
+                allocator: RingAllocator::new(capacity),
+                frames: VecDeque::new(),
+            }),
+        })
+    }
+
+    #[inline]
+    pub fn buffer_id(&self) -> &BufferId {
+        &self.buffer_id
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn is_persistent(&self) -> bool {
+        match self.storage {
+            RingStorage::Persistent(_) => true,
+            RingStorage::Orphaning => false,
+        }
+    }
+
+    /// Copies `data` into the ring at an offset that is a multiple of
+    /// `alignment` and of the alignment of `T`. Pass
+    /// `Limits::uniform_buffer_offset_alignment` for uniform ranges.
+    pub fn write<T: Copy>(
+        &self,
+        data: &[T],
+        alignment: usize,
+    ) -> Result<RingAllocation, String> {
+        let size = mem::size_of_val(data);
+        let alignment = lcm(alignment.max(1), mem::align_of::<T>());
+        if size == 0 || size > self.capacity {
+            return Err(format!(
+                "Can not allocate {} bytes from a ring buffer of {} bytes.",
+                size, self.capacity
+            ));
+        }
+
+        let mut state = self.state.borrow_mut();
+        let offset = loop {
+            if let Some(offset) = state.allocator.allocate(size, alignment) {
+                break offset;
+            }
+            // Allocations made earlier this frame may still be alive, so the
+            // only space that can be reclaimed is that of finished frames.
+            match (&self.storage, state.frames.pop_front()) {
+                (&RingStorage::Persistent(_), Some((fence, bytes))) => {
+                    fence.wait()?;
+                    state.allocator.release(bytes);
+                }
+                _ => return Err(frame_too_large(self.capacity)),
+            }
+        };
+
+        unsafe {
+            match self.storage {
+                RingStorage::Persistent(base) => {
+                    ptr::copy_nonoverlapping(data.as_ptr() as *const u8, base.add(offset), size);
+                }
+                RingStorage::Orphaning => {
+                    self.buffer_id.bind(BufferTarget::CopyWriteBuffer);
+                    let dst = gl::MapBufferRange(
+                        gl::COPY_WRITE_BUFFER,
+                        offset as GLintptr,
+                        size as GLsizeiptr,
+                        gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT
+                            | gl::MAP_UNSYNCHRONIZED_BIT,
+                    );
+                    if dst.is_null() {
+                        return Err(String::from("Failed to map ring buffer range."));
+                    }
+                    ptr::copy_nonoverlapping(data.as_ptr() as *const u8, dst as *mut u8, size);
+                    gl::UnmapBuffer(gl::COPY_WRITE_BUFFER);
+                }
+            }
+        }
+
+        Ok(RingAllocation {
+            buffer_id: &self.buffer_id,
+            offset,
+            size,
+        })
+    }
+
+    /// Marks the end of a frame. Call this after issuing the last command
+    /// that reads from this frame's allocations.
+    pub fn end_frame(&mut self) -> Result<(), String> {
+        let state = self.state.get_mut();
+        let bytes = state.allocator.end_frame();
+        match self.storage {
+            RingStorage::Persistent(_) => {
+                let fence = Fence::new()?;
+                state.frames.push_back((fence, bytes));
+            }
+            RingStorage::Orphaning => {
+                // No allocations can be alive here, so the store can be
+                // replaced. The old store stays alive for the commands that
+                // still read from it.
+                if bytes > 0 {
+                    self.buffer_id.bind(BufferTarget::CopyWriteBuffer);
+                    orphan(self.capacity);
+                    state.allocator.reset();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        if let RingStorage::Persistent(_) = self.storage {
+            self.buffer_id.bind(BufferTarget::CopyWriteBuffer);
+            unsafe {
+                gl::UnmapBuffer(gl::COPY_WRITE_BUFFER);
+            }
+        }
+    }
+}
+
+/// Replaces the data store of the buffer bound to `GL_COPY_WRITE_BUFFER`,
+/// leaving the old store to the commands still using it.
+fn orphan(capacity: usize) {
+    unsafe {
+        gl::BufferData(
+            gl::COPY_WRITE_BUFFER,
+            capacity as GLsizeiptr,
+            ptr::null(),
+            gl::STREAM_DRAW,
+        );
+    }
+}
+
+fn frame_too_large(capacity: usize) -> String {
+    format!("Frame uses more than the {} bytes in the ring buffer.", capacity)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    a / gcd(a, b) * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lcm, RingAllocator};
+
+    #[test]
+    fn ring_allocator_reuses_released_frames() {
+        let mut ring = RingAllocator::new(256);
+
+        assert_eq!(ring.allocate(100, 64), Some(0));
+        assert_eq!(ring.allocate(100, 64), Some(128));
+        let first = ring.end_frame();
+        assert_eq!(first, 228);
+
+        // 28 bytes left at the end, 28 bytes free in total.
+        assert_eq!(ring.allocate(10, 1), Some(228));
+        assert_eq!(ring.allocate(20, 1), None);
+        assert_eq!(ring.end_frame(), 10);
+
+        ring.release(first);
+        // The 18 bytes at the end are skipped when wrapping around.
+        assert_eq!(ring.allocate(20, 1), Some(0));
+        assert_eq!(ring.end_frame(), 38);
+    }
+
+    #[test]
+    fn empty_rings_start_over_at_the_front() {
+        let mut ring = RingAllocator::new(256);
+
+        assert_eq!(ring.allocate(200, 1), Some(0));
+        let first = ring.end_frame();
+        ring.release(first);
+
+        // Without starting over, the 56 bytes at the end would be skipped.
+        assert_eq!(ring.allocate(250, 1), Some(0));
+    }
+
+    #[test]
+    fn allocations_in_one_frame_are_not_reused_across_a_wrap() {
+        let mut ring = RingAllocator::new(256);
+
+        let first = ring.allocate(160, 1).unwrap();
+        let second = ring.allocate(64, 1).unwrap();
+        assert!(first + 160 <= second);
+
+        // Wrapping around would overwrite the first allocation, which is
+        // still alive until the frame ends.
+        assert_eq!(ring.allocate(64, 1), None);
+
+        // Once the frame ends and its store is orphaned the whole ring is
+        // available again.
+        ring.end_frame();
+        ring.reset();
+        assert_eq!(ring.allocate(256, 1), Some(0));
+    }
+
+    #[test]
+    fn alignments_are_combined() {
+        assert_eq!(lcm(256, 4), 256);
+        assert_eq!(lcm(6, 4), 12);
+        assert_eq!(lcm(1, 16), 16);
+    }
+}
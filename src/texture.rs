@@ -1,10 +1,13 @@
 extern crate core;
 extern crate gl;
+extern crate image;
 
 use id::Id;
 use gl::types::*;
 use std::marker::PhantomData;
 use phantomdata::into_phantom_data;
+use multisample::SampleCount;
+use buffer::{Buffer, BufferId};
 
 #[derive(Debug)]
 pub struct TextureId(Id);
@@ -40,54 +43,78 @@ impl Drop for TextureId {
 pub struct BoundTextureId<'t, 'i, TTextureTarget: 't + TextureTarget>
 {
     target: &'t mut TTextureTarget,
-    texture_id: PhantomData<&'i TextureId>,
-}
-
-pub struct TextureSlot1D;
-pub struct TextureSlot2D;
-pub struct TextureSlot3D;
-pub struct TextureSlot1DArray;
-pub struct TextureSlot2DArray;
-pub struct TextureSlotRectangle;
-pub struct TextureSlotCubeMap;
-pub struct TextureSlotCubeMapArray;
-pub struct TextureSlotBuffer;
-pub struct TextureSlot2DMultisample;
-pub struct TextureSlot2DMultisampleArray;
-
-impl TextureSlot1D {
+    texture_id: &'i TextureId,
+}
+
+pub struct TextureSlot1D<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlot2D<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlot3D<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlot1DArray<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlot2DArray<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlotRectangle<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlotCubeMap<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlotCubeMapArray<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlotBuffer<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlot2DMultisample<'a>(PhantomData<&'a mut TextureUnitSlot>);
+pub struct TextureSlot2DMultisampleArray<'a>(PhantomData<&'a mut TextureUnitSlot>);
+
+impl<'a> TextureSlot1D<'a> {
     #[inline]
     pub fn target(&mut self) -> TextureTarget1D {
         TextureTarget1D::new(self)
     }
 }
 
-impl TextureSlot2D {
+impl<'a> TextureSlot2D<'a> {
     #[inline]
     pub fn target(&mut self) -> TextureTarget2D {
         TextureTarget2D::new(self)
     }
 }
 
-impl TextureSlot3D {
+impl<'a> TextureSlot3D<'a> {
     #[inline]
     pub fn target(&mut self) -> TextureTarget3D {
         TextureTarget3D::new(self)
     }
 }
 
+impl<'a> TextureSlotBuffer<'a> {
+    #[inline]
+    pub fn target(&mut self) -> TextureTargetBuffer {
+        TextureTargetBuffer::new(self)
+    }
+}
+
+impl<'a> TextureSlot2DMultisample<'a> {
+    #[inline]
+    pub fn target(&mut self) -> TextureTarget2DMultisample {
+        TextureTarget2DMultisample::new(self)
+    }
+}
+
+impl<'a> TextureSlot2DArray<'a> {
+    #[inline]
+    pub fn target(&mut self) -> TextureTarget2DArray {
+        TextureTarget2DArray::new(self)
+    }
+}
+
 pub trait TextureTarget {
     fn as_enum(&self) -> u32;
+
+    /// The enum to query the texture bound to this target with.
+    fn binding_as_enum(&self) -> u32;
 }
 
 macro_rules! impl_texture_target(
-    ($TT:ident, $TS:ident, $enum:expr) => {
-        pub struct $TT<'s>(PhantomData<&'s mut $TS>);
+    ($TT:ident, $TS:ident, $enum:expr, $binding:expr) => {
+        pub struct $TT<'s>(PhantomData<&'s mut ()>);
 
         impl<'s> $TT<'s> {
             #[inline]
-            pub fn new(slot: &'s mut $TS) -> Self {
-                $TT(into_phantom_data(slot))
+            pub fn new<'a: 's>(_slot: &'s mut $TS<'a>) -> Self {
+                $TT(PhantomData)
             }
 
             #[inline]
@@ -97,7 +124,7 @@ macro_rules! impl_texture_target(
                 }
                 BoundTextureId {
                     target: self,
-                    texture_id: into_phantom_data(texture_id),
+                    texture_id,
                 }
             }
         }
@@ -107,21 +134,26 @@ macro_rules! impl_texture_target(
             fn as_enum(&self) -> u32 {
                 $enum
             }
+
+            #[inline]
+            fn binding_as_enum(&self) -> u32 {
+                $binding
+            }
         }
     }
 );
 
-impl_texture_target!(TextureTarget1D, TextureSlot1D, gl::TEXTURE_1D);
-impl_texture_target!(TextureTarget2D, TextureSlot2D, gl::TEXTURE_2D);
-impl_texture_target!(TextureTarget3D, TextureSlot3D, gl::TEXTURE_3D);
-impl_texture_target!(TextureTarget1DArray, TextureSlot1DArray, gl::TEXTURE_1D_ARRAY);
-impl_texture_target!(TextureTarget2DArray, TextureSlot2DArray, gl::TEXTURE_2D_ARRAY);
-impl_texture_target!(TextureTargetRectangle, TextureSlotRectangle, gl::TEXTURE_RECTANGLE);
-impl_texture_target!(TextureTargetCubeMap, TextureSlotCubeMap, gl::TEXTURE_CUBE_MAP);
-impl_texture_target!(TextureTargetCubeMapArray, TextureSlotCubeMapArray, gl::TEXTURE_CUBE_MAP_ARRAY);
-impl_texture_target!(TextureTargetBuffer, TextureSlotBuffer, gl::TEXTURE_BUFFER);
-impl_texture_target!(TextureTarget2DMultisample, TextureSlot2DMultisample, gl::TEXTURE_2D_MULTISAMPLE);
-impl_texture_target!(TextureTarget2DMultisampleArray, TextureSlot2DMultisampleArray, gl::TEXTURE_2D_MULTISAMPLE_ARRAY);
+impl_texture_target!(TextureTarget1D, TextureSlot1D, gl::TEXTURE_1D, gl::TEXTURE_BINDING_1D);
+impl_texture_target!(TextureTarget2D, TextureSlot2D, gl::TEXTURE_2D, gl::TEXTURE_BINDING_2D);
+impl_texture_target!(TextureTarget3D, TextureSlot3D, gl::TEXTURE_3D, gl::TEXTURE_BINDING_3D);
+impl_texture_target!(TextureTarget1DArray, TextureSlot1DArray, gl::TEXTURE_1D_ARRAY, gl::TEXTURE_BINDING_1D_ARRAY);
+impl_texture_target!(TextureTarget2DArray, TextureSlot2DArray, gl::TEXTURE_2D_ARRAY, gl::TEXTURE_BINDING_2D_ARRAY);
+impl_texture_target!(TextureTargetRectangle, TextureSlotRectangle, gl::TEXTURE_RECTANGLE, gl::TEXTURE_BINDING_RECTANGLE);
+impl_texture_target!(TextureTargetCubeMap, TextureSlotCubeMap, gl::TEXTURE_CUBE_MAP, gl::TEXTURE_BINDING_CUBE_MAP);
+impl_texture_target!(TextureTargetCubeMapArray, TextureSlotCubeMapArray, gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_BINDING_CUBE_MAP_ARRAY);
+impl_texture_target!(TextureTargetBuffer, TextureSlotBuffer, gl::TEXTURE_BUFFER, gl::TEXTURE_BINDING_BUFFER);
+impl_texture_target!(TextureTarget2DMultisample, TextureSlot2DMultisample, gl::TEXTURE_2D_MULTISAMPLE, gl::TEXTURE_BINDING_2D_MULTISAMPLE);
+impl_texture_target!(TextureTarget2DMultisampleArray, TextureSlot2DMultisampleArray, gl::TEXTURE_2D_MULTISAMPLE_ARRAY, gl::TEXTURE_BINDING_2D_MULTISAMPLE_ARRAY);
 
 pub trait TextureTargetGroup1DPlus: TextureTarget {}
 pub trait TextureTargetGroup2DPlus: TextureTargetGroup1DPlus {}
@@ -147,6 +179,41 @@ impl<'s> TextureTargetGroup2D for TextureTarget2D<'s> {}
 impl<'s> TextureTargetGroup2DPlus for TextureTarget2D<'s> {}
 impl<'s> TextureTargetGroup3D for TextureTarget3D<'s> {}
 impl<'s> TextureTargetGroup3DPlus for TextureTarget3D<'s> {}
+impl<'s> TextureTargetGroup2DPlus for TextureTarget2DArray<'s> {}
+
+/// Targets whose images are specified with glTexImage3D. Array textures
+/// store their layers along the depth dimension.
+pub trait TextureTargetImage3D: TextureTarget {}
+
+impl<'s> TextureTargetImage3D for TextureTarget3D<'s> {}
+impl<'s> TextureTargetImage3D for TextureTarget2DArray<'s> {}
+impl<'s> TextureTargetImage3D for TextureTargetCubeMapArray<'s> {}
+
+/// Targets whose images can be attached to a framebuffer with
+/// glFramebufferTexture2D.
+pub trait TextureTargetFramebuffer2D: TextureTarget {}
+
+impl<'s> TextureTargetFramebuffer2D for TextureTarget2D<'s> {}
+impl<'s> TextureTargetFramebuffer2D for TextureTargetRectangle<'s> {}
+impl<'s> TextureTargetFramebuffer2D for TextureTarget2DMultisample<'s> {}
+
+/// Targets of which a single layer can be attached to a framebuffer with
+/// glFramebufferTextureLayer. Cube map array layers are addressed as
+/// `6 * layer + face`.
+pub trait TextureTargetFramebufferLayer: TextureTarget {}
+
+impl<'s> TextureTargetFramebufferLayer for TextureTarget3D<'s> {}
+impl<'s> TextureTargetFramebufferLayer for TextureTarget1DArray<'s> {}
+impl<'s> TextureTargetFramebufferLayer for TextureTarget2DArray<'s> {}
+impl<'s> TextureTargetFramebufferLayer for TextureTargetCubeMapArray<'s> {}
+impl<'s> TextureTargetFramebufferLayer for TextureTarget2DMultisampleArray<'s> {}
+
+/// Targets whose layers can all be attached to a framebuffer at once with
+/// glFramebufferTexture, for layered rendering from a geometry shader.
+pub trait TextureTargetFramebufferLayered: TextureTarget {}
+
+impl<'s, T: TextureTargetFramebufferLayer> TextureTargetFramebufferLayered for T {}
+impl<'s> TextureTargetFramebufferLayered for TextureTargetCubeMap<'s> {}
 
 impl<'t, 'i, TTextureTarget: 't + TextureTarget>
     BoundTextureId<'t, 'i, TTextureTarget>
@@ -163,6 +230,45 @@ impl<'t, 'i, TTextureTarget: 't + TextureTarget>
         self.target.as_enum()
     }
 
+    #[inline]
+    pub fn texture_id(&self) -> &'i TextureId {
+        self.texture_id
+    }
+
+    /// Whether the texture is actually bound to the target. Binding fails
+    /// when the texture was first bound to a different target.
+    pub fn is_bound(&self) -> bool {
+        let mut value: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(self.target.binding_as_enum(), &mut value);
+            value as GLuint == self.texture_id.as_u32()
+        }
+    }
+
+    /// glGetTexLevelParameteriv. Cube maps are queried through their
+    /// positive x face.
+    pub fn level_parameter_i(&self, mipmap_level: GLint, param: GLenum) -> GLint {
+        let target = match self.target.as_enum() {
+            gl::TEXTURE_CUBE_MAP => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            target => target,
+        };
+        let mut value: GLint = 0;
+        unsafe {
+            gl::GetTexLevelParameteriv(target, mipmap_level, param, &mut value);
+        }
+        value
+    }
+
+    /// The width, height and depth of a mipmap level. All are zero when the
+    /// level has not been specified.
+    pub fn level_size(&self, mipmap_level: GLint) -> (GLsizei, GLsizei, GLsizei) {
+        (
+            self.level_parameter_i(mipmap_level, gl::TEXTURE_WIDTH),
+            self.level_parameter_i(mipmap_level, gl::TEXTURE_HEIGHT),
+            self.level_parameter_i(mipmap_level, gl::TEXTURE_DEPTH),
+        )
+    }
+
     #[inline]
     fn parameter_i(&mut self, param: GLenum, value: GLint) -> &mut Self {
         unsafe {
@@ -181,6 +287,16 @@ impl<'t, 'i, TTextureTarget: 't + TextureTarget>
         self.parameter_i(gl::TEXTURE_MAG_FILTER, value as GLint)
     }
 
+    #[inline]
+    pub fn base_level(&mut self, value: GLint) -> &mut Self {
+        self.parameter_i(gl::TEXTURE_BASE_LEVEL, value)
+    }
+
+    #[inline]
+    pub fn max_level(&mut self, value: GLint) -> &mut Self {
+        self.parameter_i(gl::TEXTURE_MAX_LEVEL, value)
+    }
+
     #[inline]
     pub fn generate_mipmap(&mut self) -> &mut Self {
         unsafe {
@@ -247,6 +363,327 @@ impl<'t, 'i, TTextureTarget: 't + TextureTargetGroup2D>
     }
 }
 
+impl<'t, 'i, TTextureTarget: 't + TextureTargetImage3D>
+    BoundTextureId<'t, 'i, TTextureTarget>
+{
+    #[inline]
+    pub unsafe fn image_3d(
+        &mut self,
+        mipmap_level: GLint,
+        internal_format: GLint,
+        width: GLint,
+        height: GLint,
+        depth: GLint,
+        format: GLenum,
+        component_format: GLenum,
+        data: *const GLvoid,
+    ) -> &mut Self {
+        gl::TexImage3D(
+            self.target.as_enum(),
+            mipmap_level,
+            internal_format,
+            width,
+            height,
+            depth,
+            0, // border, must be zero
+            format,
+            component_format,
+            data,
+        );
+        self
+    }
+
+    #[inline]
+    pub unsafe fn sub_image_3d(
+        &mut self,
+        mipmap_level: GLint,
+        x: GLint,
+        y: GLint,
+        z: GLint,
+        width: GLint,
+        height: GLint,
+        depth: GLint,
+        format: GLenum,
+        component_format: GLenum,
+        data: *const GLvoid,
+    ) -> &mut Self {
+        gl::TexSubImage3D(
+            self.target.as_enum(),
+            mipmap_level,
+            x,
+            y,
+            z,
+            width,
+            height,
+            depth,
+            format,
+            component_format,
+            data,
+        );
+        self
+    }
+}
+
+impl<'s: 't, 't, 'i> BoundTextureId<'t, 'i, TextureTarget2DArray<'s>> {
+    /// Uploads a single layer of a 2D array texture. Storage for the layer
+    /// must have been allocated with `image_3d` first.
+    #[inline]
+    pub unsafe fn layer_image_2d(
+        &mut self,
+        mipmap_level: GLint,
+        layer: GLint,
+        width: GLint,
+        height: GLint,
+        format: GLenum,
+        component_format: GLenum,
+        data: *const GLvoid,
+    ) -> &mut Self {
+        self.sub_image_3d(
+            mipmap_level,
+            0,
+            0,
+            layer,
+            width,
+            height,
+            1,
+            format,
+            component_format,
+            data,
+        )
+    }
+}
+
+impl<'s: 't, 't, 'i> BoundTextureId<'t, 'i, TextureTargetBuffer<'s>> {
+    /// Exposes the whole buffer to shaders as a `samplerBuffer`, each texel
+    /// interpreted according to `format`.
+    #[inline]
+    pub fn buffer(&mut self, format: TextureBufferFormat, buffer: &BufferId) -> &mut Self {
+        unsafe {
+            gl::TexBuffer(self.target.as_enum(), format as GLenum, buffer.as_uint());
+        }
+        self
+    }
+
+    /// Exposes `size` bytes of the buffer starting at `offset`. The offset
+    /// must be a multiple of `GL_TEXTURE_BUFFER_OFFSET_ALIGNMENT`, the size
+    /// a whole number of texels and the range must lie within the buffer.
+    /// Requires OpenGL 4.3 or ARB_texture_buffer_range.
+    pub fn buffer_range<T: Copy>(
+        &mut self,
+        format: TextureBufferFormat,
+        buffer: &Buffer<T>,
+        offset: usize,
+        size: usize,
+    ) -> Result<&mut Self, String> {
+        let mut offset_alignment: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::TEXTURE_BUFFER_OFFSET_ALIGNMENT, &mut offset_alignment);
+        }
+        check_buffer_range(
+            offset,
+            size,
+            buffer.byte_len(),
+            format.bytes_per_texel(),
+            offset_alignment as usize,
+        )?;
+        unsafe {
+            gl::TexBufferRange(
+                self.target.as_enum(),
+                format as GLenum,
+                buffer.id().as_uint(),
+                offset as GLintptr,
+                size as GLsizeiptr,
+            );
+        }
+        Ok(self)
+    }
+}
+
+/// Checks that a texture buffer range starts at a multiple of
+/// `offset_alignment`, holds a non-zero whole number of texels and lies
+/// within a buffer of `byte_len` bytes.
+fn check_buffer_range(
+    offset: usize,
+    size: usize,
+    byte_len: usize,
+    bytes_per_texel: usize,
+    offset_alignment: usize,
+) -> Result<(), String> {
+    if offset_alignment != 0 && offset % offset_alignment != 0 {
+        return Err(format!(
+            "Texture buffer offset {} is not a multiple of GL_TEXTURE_BUFFER_OFFSET_ALIGNMENT ({}).",
+            offset, offset_alignment
+        ));
+    }
+    if size == 0 || size % bytes_per_texel != 0 {
+        return Err(format!(
+            "Texture buffer size {} is not a positive multiple of the texel size {}.",
+            size, bytes_per_texel
+        ));
+    }
+    if offset.checked_add(size).map_or(true, |end| end > byte_len) {
+        return Err(format!(
+            "Texture buffer range of {} bytes at offset {} exceeds the buffer size {}.",
+            size, offset, byte_len
+        ));
+    }
+    Ok(())
+}
+
+impl<'s: 't, 't, 'i> BoundTextureId<'t, 'i, TextureTarget2DMultisample<'s>> {
+    /// Allocates storage for a multisampled texture. With
+    /// `fixed_sample_locations` the sample positions are identical for all
+    /// texels and do not depend on the internal format or size. Fails when
+    /// textures of `internal_format` do not support `samples`.
+    pub fn image_2d_multisample(
+        &mut self,
+        samples: SampleCount,
+        internal_format: GLenum,
+        width: GLsizei,
+        height: GLsizei,
+        fixed_sample_locations: bool,
+    ) -> Result<&mut Self, String> {
+        samples.check_texture(internal_format)?;
+        unsafe {
+            gl::TexImage2DMultisample(
+                self.target.as_enum(),
+                samples.get(),
+                internal_format,
+                width,
+                height,
+                fixed_sample_locations as GLboolean,
+            );
+        }
+        Ok(self)
+    }
+}
+
+/// Texel formats a buffer texture can interpret its buffer as. The RGB32
+/// formats require OpenGL 4.0 or ARB_texture_buffer_object_rgb32.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+#[allow(non_camel_case_types)]
+pub enum TextureBufferFormat {
+    R8 = gl::R8,
+    R16 = gl::R16,
+    R16F = gl::R16F,
+    R32F = gl::R32F,
+    R8I = gl::R8I,
+    R16I = gl::R16I,
+    R32I = gl::R32I,
+    R8UI = gl::R8UI,
+    R16UI = gl::R16UI,
+    R32UI = gl::R32UI,
+    RG8 = gl::RG8,
+    RG16 = gl::RG16,
+    RG16F = gl::RG16F,
+    RG32F = gl::RG32F,
+    RG8I = gl::RG8I,
+    RG16I = gl::RG16I,
+    RG32I = gl::RG32I,
+    RG8UI = gl::RG8UI,
+    RG16UI = gl::RG16UI,
+    RG32UI = gl::RG32UI,
+    RGB32F = gl::RGB32F,
+    RGB32I = gl::RGB32I,
+    RGB32UI = gl::RGB32UI,
+    RGBA8 = gl::RGBA8,
+    RGBA16 = gl::RGBA16,
+    RGBA16F = gl::RGBA16F,
+    RGBA32F = gl::RGBA32F,
+    RGBA8I = gl::RGBA8I,
+    RGBA16I = gl::RGBA16I,
+    RGBA32I = gl::RGBA32I,
+    RGBA8UI = gl::RGBA8UI,
+    RGBA16UI = gl::RGBA16UI,
+    RGBA32UI = gl::RGBA32UI,
+}
+
+impl TextureBufferFormat {
+    pub fn bytes_per_texel(&self) -> usize {
+        use self::TextureBufferFormat::*;
+        match *self {
+            R8 | R8I | R8UI => 1,
+            R16 | R16F | R16I | R16UI | RG8 | RG8I | RG8UI => 2,
+            R32F | R32I | R32UI | RG16 | RG16F | RG16I | RG16UI | RGBA8 | RGBA8I | RGBA8UI => 4,
+            RG32F | RG32I | RG32UI | RGBA16 | RGBA16F | RGBA16I | RGBA16UI => 8,
+            RGB32F | RGB32I | RGB32UI => 12,
+            RGBA32F | RGBA32I | RGBA32UI => 16,
+        }
+    }
+}
+
+/// Pixel types that can be transferred between `image` buffers and
+/// textures. Describes the client side layout of a pixel, the internal
+/// format of the texture is chosen separately.
+pub trait TexturePixel: image::Pixel + 'static {
+    /// The pixel format, for example `GL_RGBA`.
+    const FORMAT: GLenum;
+
+    /// The component format, for example `GL_UNSIGNED_BYTE`.
+    const COMPONENT_FORMAT: GLenum;
+}
+
+macro_rules! impl_texture_pixel {
+    ($Pixel:ty, $format:expr, $component_format:expr) => {
+        impl TexturePixel for $Pixel {
+            const FORMAT: GLenum = $format;
+            const COMPONENT_FORMAT: GLenum = $component_format;
+        }
+    }
+}
+
+impl_texture_pixel!(image::Luma<u8>, gl::RED, gl::UNSIGNED_BYTE);
+impl_texture_pixel!(image::Rgb<u8>, gl::RGB, gl::UNSIGNED_BYTE);
+impl_texture_pixel!(image::Rgba<u8>, gl::RGBA, gl::UNSIGNED_BYTE);
+impl_texture_pixel!(image::Luma<u16>, gl::RED, gl::UNSIGNED_SHORT);
+impl_texture_pixel!(image::Rgb<u16>, gl::RGB, gl::UNSIGNED_SHORT);
+impl_texture_pixel!(image::Rgba<u16>, gl::RGBA, gl::UNSIGNED_SHORT);
+impl_texture_pixel!(image::Luma<f32>, gl::RED, gl::FLOAT);
+impl_texture_pixel!(image::Rgb<f32>, gl::RGB, gl::FLOAT);
+impl_texture_pixel!(image::Rgba<f32>, gl::RGBA, gl::FLOAT);
+
+/// Runs `f` with `alignment`, either `GL_PACK_ALIGNMENT` or
+/// `GL_UNPACK_ALIGNMENT`, set to 1 so that the rows of `image` buffers are
+/// transferred tightly packed. The previous alignment is restored after.
+pub(crate) unsafe fn with_tight_rows<R, F: FnOnce() -> R>(alignment: GLenum, f: F) -> R {
+    let mut previous: GLint = 0;
+    gl::GetIntegerv(alignment, &mut previous);
+    gl::PixelStorei(alignment, 1);
+    let result = f();
+    gl::PixelStorei(alignment, previous);
+    result
+}
+
+impl<'t, 'i, TTextureTarget: 't + TextureTargetGroup2D>
+    BoundTextureId<'t, 'i, TTextureTarget>
+{
+    /// Uploads an image buffer as the given mipmap level. Pass a floating
+    /// point internal format like `GL_RGB16F` to keep the full range of
+    /// HDR images.
+    pub fn image_2d_from<P: TexturePixel>(
+        &mut self,
+        mipmap_level: GLint,
+        internal_format: GLint,
+        image: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+    ) -> &mut Self {
+        unsafe {
+            with_tight_rows(gl::UNPACK_ALIGNMENT, || {
+                self.image_2d(
+                    mipmap_level,
+                    internal_format,
+                    image.width() as GLint,
+                    image.height() as GLint,
+                    P::FORMAT,
+                    P::COMPONENT_FORMAT,
+                    image.as_ptr() as *const GLvoid,
+                );
+            });
+        }
+        self
+    }
+}
+
 // NOTE(mickvangelderen): It would be a mistake to implement drop like this.
 // The following code requires the texture to stay bound when switching
 // texture units.
@@ -264,9 +701,14 @@ impl<'t, 'i, TTextureTarget: 't + TextureTargetGroup2D>
 //     }
 // }
 
-pub struct TextureUnitSlot;
+pub struct TextureUnitSlot(());
 
 impl TextureUnitSlot {
+    #[inline]
+    pub(crate) unsafe fn new() -> Self {
+        TextureUnitSlot(())
+    }
+
     #[inline]
     pub fn activate(&mut self, unit: TextureUnit) -> ActiveTextureUnit {
         unsafe {
@@ -275,17 +717,17 @@ impl TextureUnitSlot {
 
         ActiveTextureUnit {
             texture_unit_slot: into_phantom_data(self),
-            texture_slot_1d: TextureSlot1D,
-            texture_slot_2d: TextureSlot2D,
-            texture_slot_3d: TextureSlot3D,
-            texture_slot_1d_array: TextureSlot1DArray,
-            texture_slot_2d_array: TextureSlot2DArray,
-            texture_slot_rectangle: TextureSlotRectangle,
-            texture_slot_cube_map: TextureSlotCubeMap,
-            texture_slot_cube_map_array: TextureSlotCubeMapArray,
-            texture_slot_buffer: TextureSlotBuffer,
-            texture_slot_2d_multisample: TextureSlot2DMultisample,
-            texture_slot_2d_multisample_array: TextureSlot2DMultisampleArray,
+            texture_slot_1d: TextureSlot1D(PhantomData),
+            texture_slot_2d: TextureSlot2D(PhantomData),
+            texture_slot_3d: TextureSlot3D(PhantomData),
+            texture_slot_1d_array: TextureSlot1DArray(PhantomData),
+            texture_slot_2d_array: TextureSlot2DArray(PhantomData),
+            texture_slot_rectangle: TextureSlotRectangle(PhantomData),
+            texture_slot_cube_map: TextureSlotCubeMap(PhantomData),
+            texture_slot_cube_map_array: TextureSlotCubeMapArray(PhantomData),
+            texture_slot_buffer: TextureSlotBuffer(PhantomData),
+            texture_slot_2d_multisample: TextureSlot2DMultisample(PhantomData),
+            texture_slot_2d_multisample_array: TextureSlot2DMultisampleArray(PhantomData),
         }
     }
 }
@@ -296,19 +738,30 @@ pub enum TextureUnit {
     TextureUnit1 = gl::TEXTURE1,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CubeMapFace {
+    PositiveX = gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+    NegativeX = gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+    PositiveY = gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+    NegativeY = gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+    PositiveZ = gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+    NegativeZ = gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+}
+
 pub struct ActiveTextureUnit<'a> {
     texture_unit_slot: PhantomData<&'a mut TextureUnitSlot>,
-    pub texture_slot_1d: TextureSlot1D,
-    pub texture_slot_2d: TextureSlot2D,
-    pub texture_slot_3d: TextureSlot3D,
-    pub texture_slot_1d_array: TextureSlot1DArray,
-    pub texture_slot_2d_array: TextureSlot2DArray,
-    pub texture_slot_rectangle: TextureSlotRectangle,
-    pub texture_slot_cube_map: TextureSlotCubeMap,
-    pub texture_slot_cube_map_array: TextureSlotCubeMapArray,
-    pub texture_slot_buffer: TextureSlotBuffer,
-    pub texture_slot_2d_multisample: TextureSlot2DMultisample,
-    pub texture_slot_2d_multisample_array: TextureSlot2DMultisampleArray,
+    pub texture_slot_1d: TextureSlot1D<'a>,
+    pub texture_slot_2d: TextureSlot2D<'a>,
+    pub texture_slot_3d: TextureSlot3D<'a>,
+    pub texture_slot_1d_array: TextureSlot1DArray<'a>,
+    pub texture_slot_2d_array: TextureSlot2DArray<'a>,
+    pub texture_slot_rectangle: TextureSlotRectangle<'a>,
+    pub texture_slot_cube_map: TextureSlotCubeMap<'a>,
+    pub texture_slot_cube_map_array: TextureSlotCubeMapArray<'a>,
+    pub texture_slot_buffer: TextureSlotBuffer<'a>,
+    pub texture_slot_2d_multisample: TextureSlot2DMultisample<'a>,
+    pub texture_slot_2d_multisample_array: TextureSlot2DMultisampleArray<'a>,
 }
 
 #[repr(u32)]
@@ -352,3 +805,26 @@ pub enum TextureFilter {
     /// those two values.
     LinearMipmapLinear = gl::LINEAR_MIPMAP_LINEAR,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::check_buffer_range;
+
+    #[test]
+    fn buffer_ranges_are_aligned_and_whole_texels() {
+        assert!(check_buffer_range(256, 48, 1024, 16, 256).is_ok());
+        assert!(check_buffer_range(0, 16, 1024, 16, 256).is_ok());
+        assert!(check_buffer_range(128, 48, 1024, 16, 256).is_err());
+        assert!(check_buffer_range(256, 40, 1024, 16, 256).is_err());
+        assert!(check_buffer_range(256, 0, 1024, 16, 256).is_err());
+    }
+
+    #[test]
+    fn buffer_ranges_lie_within_the_buffer() {
+        assert!(check_buffer_range(768, 256, 1024, 16, 256).is_ok());
+        assert!(check_buffer_range(768, 272, 1024, 16, 256).is_err());
+        assert!(check_buffer_range(1024, 16, 1024, 16, 256).is_err());
+        // Would wrap around when added.
+        assert!(check_buffer_range(usize::max_value() - 255, 256, 1024, 16, 256).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+extern crate gl;
+extern crate image;
+
+use gl::types::*;
+use texture::{with_tight_rows, TextureId, TextureTarget2DArray};
+
+/// Packs a list of images with identical dimensions into a
+/// `GL_TEXTURE_2D_ARRAY`, one image per layer.
+///
+/// Every layer is either given only its base level, in which case the
+/// remaining levels are generated by the driver, or a mip chain starting at
+/// the base level. All layers must be given the same number of levels. The
+/// maximum level of the texture is set to the last given level so partial
+/// chains are mipmap complete.
+#[derive(Debug)]
+pub struct TextureArrayBuilder {
+    layers: Vec<Vec<image::RgbaImage>>,
+}
+
+/// The number of levels in a full mip chain for an image of the given
+/// dimensions.
+pub fn mipmap_level_count(width: u32, height: u32) -> u32 {
+    32 - ::std::cmp::max(width, height).leading_zeros()
+}
+
+impl TextureArrayBuilder {
+    pub fn new() -> Self {
+        TextureArrayBuilder { layers: Vec::new() }
+    }
+
+    pub fn layer(&mut self, image: image::RgbaImage) -> &mut Self {
+        self.layers.push(vec![image]);
+        self
+    }
+
+    /// Adds a layer together with its mip chain, starting at level 0.
+    pub fn layer_with_mipmaps(&mut self, levels: Vec<image::RgbaImage>) -> &mut Self {
+        self.layers.push(levels);
+        self
+    }
+
+    fn validate(&self) -> Result<(u32, u32, usize), String> {
+        let (width, height, level_count) = match self.layers.first() {
+            Some(levels) => match levels.first() {
+                Some(base) => (base.width(), base.height(), levels.len()),
+                None => return Err(String::from("Layer 0 has no images.")),
+            },
+            None => return Err(String::from("Texture array has no layers.")),
+        };
+
+        if level_count as u32 > mipmap_level_count(width, height) {
+            return Err(format!(
+                "Layer 0 has {} levels but a {}x{} image has at most {}.",
+                level_count,
+                width,
+                height,
+                mipmap_level_count(width, height)
+            ));
+        }
+
+        for (layer, levels) in self.layers.iter().enumerate() {
+            if levels.len() != level_count {
+                return Err(format!(
+                    "Layer {} has {} levels, expected {}.",
+                    layer,
+                    levels.len(),
+                    level_count
+                ));
+            }
+
+            for (level, image) in levels.iter().enumerate() {
+                let expected = (
+                    ::std::cmp::max(1, width >> level),
+                    ::std::cmp::max(1, height >> level),
+                );
+                if image.dimensions() != expected {
+                    return Err(format!(
+                        "Layer {} level {} is {}x{}, expected {}x{}.",
+                        layer,
+                        level,
+                        image.width(),
+                        image.height(),
+                        expected.0,
+                        expected.1
+                    ));
+                }
+            }
+        }
+
+        Ok((width, height, level_count))
+    }
+
+    /// Allocates and uploads the texture array. The texture is left bound
+    /// to the given target.
+    pub fn build(&self, target: &mut TextureTarget2DArray) -> Result<TextureId, String> {
+        let (width, height, level_count) = self.validate()?;
+
+        let texture_id =
+            TextureId::new().ok_or_else(|| String::from("Failed to acquire texture id."))?;
+
+        unsafe {
+            let mut bound = target.bind(&texture_id);
+
+            // Allocate every level for every layer up front.
+            let allocated_levels = if level_count == 1 {
+                mipmap_level_count(width, height) as usize
+            } else {
+                level_count
+            };
+
+            for level in 0..allocated_levels {
+                bound.image_3d(
+                    level as GLint,
+                    gl::RGBA8 as GLint,
+                    ::std::cmp::max(1, width >> level) as GLint,
+                    ::std::cmp::max(1, height >> level) as GLint,
+                    self.layers.len() as GLint,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    ::std::ptr::null(),
+                );
+            }
+
+            with_tight_rows(gl::UNPACK_ALIGNMENT, || {
+                for (layer, levels) in self.layers.iter().enumerate() {
+                    for (level, image) in levels.iter().enumerate() {
+                        bound.layer_image_2d(
+                            level as GLint,
+                            layer as GLint,
+                            image.width() as GLint,
+                            image.height() as GLint,
+                            gl::RGBA,
+                            gl::UNSIGNED_BYTE,
+                            image.as_ptr() as *const GLvoid,
+                        );
+                    }
+                }
+            });
+
+            bound.max_level(allocated_levels as GLint - 1);
+
+            if level_count == 1 {
+                bound.generate_mipmap();
+            }
+        }
+
+        Ok(texture_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mipmap_level_count_is_log2_of_largest_dimension_plus_1() {
+        assert_eq!(1, mipmap_level_count(1, 1));
+        assert_eq!(2, mipmap_level_count(2, 1));
+        assert_eq!(9, mipmap_level_count(256, 256));
+        assert_eq!(9, mipmap_level_count(300, 17));
+    }
+
+    #[test]
+    fn rejects_layers_with_different_sizes() {
+        let mut builder = TextureArrayBuilder::new();
+        builder
+            .layer(image::RgbaImage::new(4, 4))
+            .layer(image::RgbaImage::new(4, 2));
+        assert!(builder.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_mip_chains_with_wrong_sizes() {
+        let mut builder = TextureArrayBuilder::new();
+        builder.layer_with_mipmaps(vec![
+            image::RgbaImage::new(4, 4),
+            image::RgbaImage::new(2, 2),
+            image::RgbaImage::new(2, 2),
+        ]);
+        assert!(builder.validate().is_err());
+    }
+
+    #[test]
+    fn accepts_layers_with_complete_mip_chains() {
+        let mut builder = TextureArrayBuilder::new();
+        for _ in 0..2 {
+            builder.layer_with_mipmaps(vec![
+                image::RgbaImage::new(4, 2),
+                image::RgbaImage::new(2, 1),
+                image::RgbaImage::new(1, 1),
+            ]);
+        }
+        assert_eq!(Ok((4, 2, 3)), builder.validate());
+    }
+}
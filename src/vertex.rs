@@ -0,0 +1,309 @@
+extern crate cgmath;
+extern crate gl;
+
+use gl::types::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum AttributeType {
+    Byte = gl::BYTE,
+    UnsignedByte = gl::UNSIGNED_BYTE,
+    Short = gl::SHORT,
+    UnsignedShort = gl::UNSIGNED_SHORT,
+    Int = gl::INT,
+    UnsignedInt = gl::UNSIGNED_INT,
+    HalfFloat = gl::HALF_FLOAT,
+    Float = gl::FLOAT,
+    Double = gl::DOUBLE,
+}
+
+impl AttributeType {
+    /// The size of a single component in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match *self {
+            AttributeType::Byte | AttributeType::UnsignedByte => 1,
+            AttributeType::Short | AttributeType::UnsignedShort | AttributeType::HalfFloat => 2,
+            AttributeType::Int | AttributeType::UnsignedInt | AttributeType::Float => 4,
+            AttributeType::Double => 8,
+        }
+    }
+
+    #[inline]
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            AttributeType::HalfFloat | AttributeType::Float | AttributeType::Double => false,
+            _ => true,
+        }
+    }
+}
+
+/// The number and type of components of a vertex attribute field.
+pub trait AttributeFormat {
+    const COMPONENTS: GLint;
+    const COMPONENT_TYPE: AttributeType;
+    /// The number of consecutive locations the field occupies. Matrices use
+    /// one location per column.
+    const COLUMNS: GLuint = 1;
+}
+
+macro_rules! impl_attribute_format {
+    ($T:ty, $component_type:ident) => {
+        impl AttributeFormat for $T {
+            const COMPONENTS: GLint = 1;
+            const COMPONENT_TYPE: AttributeType = AttributeType::$component_type;
+        }
+
+        impl_attribute_format!($T, $component_type, [1, 2, 3, 4]);
+
+        impl AttributeFormat for cgmath::Vector2<$T> {
+            const COMPONENTS: GLint = 2;
+            const COMPONENT_TYPE: AttributeType = AttributeType::$component_type;
+        }
+
+        impl AttributeFormat for cgmath::Vector3<$T> {
+            const COMPONENTS: GLint = 3;
+            const COMPONENT_TYPE: AttributeType = AttributeType::$component_type;
+        }
+
+        impl AttributeFormat for cgmath::Vector4<$T> {
+            const COMPONENTS: GLint = 4;
+            const COMPONENT_TYPE: AttributeType = AttributeType::$component_type;
+        }
+    };
+    ($T:ty, $component_type:ident, [$($n:expr),*]) => {
+        $(
+            impl AttributeFormat for [$T; $n] {
+                const COMPONENTS: GLint = $n;
+                const COMPONENT_TYPE: AttributeType = AttributeType::$component_type;
+            }
+        )*
+    };
+}
+
+impl_attribute_format!(i8, Byte);
+impl_attribute_format!(u8, UnsignedByte);
+impl_attribute_format!(i16, Short);
+impl_attribute_format!(u16, UnsignedShort);
+impl_attribute_format!(i32, Int);
+impl_attribute_format!(u32, UnsignedInt);
+impl_attribute_format!(f32, Float);
+impl_attribute_format!(f64, Double);
+
+/// Attribute formats with integer components, which can be passed to the
+/// shader as integers with `#[vertex(integer)]`.
+pub trait IntegerAttributeFormat: AttributeFormat {}
+
+macro_rules! impl_integer_attribute_format {
+    ($($T:ty),*) => {
+        $(
+            impl IntegerAttributeFormat for $T {}
+            impl IntegerAttributeFormat for [$T; 1] {}
+            impl IntegerAttributeFormat for [$T; 2] {}
+            impl IntegerAttributeFormat for [$T; 3] {}
+            impl IntegerAttributeFormat for [$T; 4] {}
+            impl IntegerAttributeFormat for cgmath::Vector2<$T> {}
+            impl IntegerAttributeFormat for cgmath::Vector3<$T> {}
+            impl IntegerAttributeFormat for cgmath::Vector4<$T> {}
+        )*
+    };
+}
+
+impl_integer_attribute_format!(i8, u8, i16, u16, i32, u32);
+
+impl AttributeFormat for cgmath::Matrix2<f32> {
+    const COMPONENTS: GLint = 2;
+    const COMPONENT_TYPE: AttributeType = AttributeType::Float;
+    const COLUMNS: GLuint = 2;
+}
+
+impl AttributeFormat for cgmath::Matrix3<f32> {
+    const COMPONENTS: GLint = 3;
+    const COMPONENT_TYPE: AttributeType = AttributeType::Float;
+    const COLUMNS: GLuint = 3;
+}
+
+impl AttributeFormat for cgmath::Matrix4<f32> {
+    const COMPONENTS: GLint = 4;
+    const COMPONENT_TYPE: AttributeType = AttributeType::Float;
+    const COLUMNS: GLuint = 4;
+}
+
+/// Describes how a single attribute is read from a vertex.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VertexAttribute {
+    pub location: GLuint,
+    pub components: GLint,
+    pub component_type: AttributeType,
+    /// The number of consecutive locations, starting at `location`, that
+    /// each hold one column of `components` components.
+    pub columns: GLuint,
+    /// Map integer components to [0, 1] or [-1, 1].
+    pub normalized: bool,
+    /// Pass integer components to the shader as integers instead of
+    /// converting them to floats.
+    pub integer: bool,
+    /// The offset in bytes from the start of the vertex.
+    pub offset: usize,
+    /// Advance the attribute once every `divisor` instances instead of once
+    /// per vertex when non-zero.
+    pub divisor: GLuint,
+}
+
+impl VertexAttribute {
+    /// The location and offset in bytes from the start of the vertex of
+    /// every column.
+    pub fn column_locations(&self) -> Vec<(GLuint, usize)> {
+        let column_size = self.components as usize * self.component_type.size();
+        (0..self.columns)
+            .map(|column| {
+                (
+                    self.location + column,
+                    self.offset + column as usize * column_size,
+                )
+            })
+            .collect()
+    }
+
+    /// Issues the `glVertexAttrib*Pointer` calls for this attribute of a
+    /// vertex of `stride` bytes whose first vertex starts `buffer_offset`
+    /// bytes into the buffer, sets its divisor and enables it. Reads from
+    /// the buffer bound to `GL_ARRAY_BUFFER`.
+    pub unsafe fn pointer(&self, stride: usize, buffer_offset: usize) {
+        debug_assert!(
+            !self.integer || self.component_type.is_integer(),
+            "Integer attributes need an integer component type."
+        );
+        for (location, offset) in self.column_locations() {
+            let offset = (buffer_offset + offset) as *const GLvoid;
+            if self.integer {
+                gl::VertexAttribIPointer(
+                    location,
+                    self.components,
+                    self.component_type as GLenum,
+                    stride as GLsizei,
+                    offset,
+                );
+            } else {
+                gl::VertexAttribPointer(
+                    location,
+                    self.components,
+                    self.component_type as GLenum,
+                    if self.normalized { gl::TRUE } else { gl::FALSE },
+                    stride as GLsizei,
+                    offset,
+                );
+            }
+            gl::VertexAttribDivisor(location, self.divisor);
+            gl::EnableVertexAttribArray(location);
+        }
+    }
+
+    /// Issues the `glVertexAttrib*Format` and `glVertexAttribBinding` calls
+    /// that read this attribute from vertex buffer binding `binding`, and
+    /// enables it. Requires GL 4.3 or `ARB_vertex_attrib_binding`.
+    pub unsafe fn format(&self, binding: GLuint) {
+        debug_assert!(
+            !self.integer || self.component_type.is_integer(),
+            "Integer attributes need an integer component type."
+        );
+        for (location, offset) in self.column_locations() {
+            if self.integer {
+                gl::VertexAttribIFormat(
+                    location,
+                    self.components,
+                    self.component_type as GLenum,
+                    offset as GLuint,
+                );
+            } else {
+                gl::VertexAttribFormat(
+                    location,
+                    self.components,
+                    self.component_type as GLenum,
+                    if self.normalized { gl::TRUE } else { gl::FALSE },
+                    offset as GLuint,
+                );
+            }
+            gl::VertexAttribBinding(location, binding);
+            gl::EnableVertexAttribArray(location);
+        }
+    }
+}
+
+/// A vertex type whose attributes can be configured on a vertex array.
+/// Implement it with `#[derive(Vertex)]` from `vertex-derive`.
+///
+/// Unsafe because the attributes must lie within the vertex and match the
+/// types of the fields they are read from.
+pub unsafe trait Vertex: Copy {
+    fn attributes() -> Vec<VertexAttribute>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Vertex)]
+    #[repr(C)]
+    #[allow(unused)]
+    struct TestVertex {
+        #[vertex(location = 0)]
+        position: cgmath::Vector3<f32>,
+        padding: u32,
+        #[vertex(location = 3, normalized)]
+        color: [u8; 4],
+        #[vertex(location = 4, integer)]
+        material: u16,
+        #[vertex(location = 5, divisor = 1)]
+        pos_from_obj_to_wld_space: cgmath::Matrix4<f32>,
+    }
+
+    #[test]
+    fn derive_describes_marked_fields() {
+        assert_eq!(
+            TestVertex::attributes(),
+            vec![
+                VertexAttribute {
+                    location: 0,
+                    components: 3,
+                    component_type: AttributeType::Float,
+                    columns: 1,
+                    normalized: false,
+                    integer: false,
+                    offset: 0,
+                    divisor: 0,
+                },
+                VertexAttribute {
+                    location: 3,
+                    components: 4,
+                    component_type: AttributeType::UnsignedByte,
+                    columns: 1,
+                    normalized: true,
+                    integer: false,
+                    offset: 16,
+                    divisor: 0,
+                },
+                VertexAttribute {
+                    location: 4,
+                    components: 1,
+                    component_type: AttributeType::UnsignedShort,
+                    columns: 1,
+                    normalized: false,
+                    integer: true,
+                    offset: 20,
+                    divisor: 0,
+                },
+                VertexAttribute {
+                    location: 5,
+                    components: 4,
+                    component_type: AttributeType::Float,
+                    columns: 4,
+                    normalized: false,
+                    integer: false,
+                    offset: 24,
+                    divisor: 1,
+                },
+            ]
+        );
+    }
+}
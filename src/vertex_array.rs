@@ -1,8 +1,113 @@
 extern crate core;
 extern crate gl;
 
+use buffer::{Buffer, BufferTarget};
+use context::Capabilities;
 use gl::types::*;
 use id::Id;
+use std::any::TypeId;
+use std::mem;
+use vertex::{Vertex, VertexAttribute};
+
+#[derive(Clone, Debug)]
+struct VertexBinding {
+    index: GLuint,
+    vertex: TypeId,
+    stride: usize,
+    divisor: GLuint,
+    attributes: Vec<VertexAttribute>,
+}
+
+/// The divisor shared by all `attributes`. A vertex buffer binding has a
+/// single divisor, so per-vertex and per-instance attributes need separate
+/// bindings.
+fn binding_divisor(attributes: &[VertexAttribute]) -> Result<GLuint, String> {
+    let divisor = attributes.first().map(|a| a.divisor).unwrap_or(0);
+    if attributes.iter().all(|a| a.divisor == divisor) {
+        Ok(divisor)
+    } else {
+        Err(String::from(
+            "All attributes of a vertex buffer binding must have the same divisor.",
+        ))
+    }
+}
+
+/// Checks that `attributes` do not use a location twice or a location that
+/// is already used by `bindings`.
+fn check_locations(
+    bindings: &[VertexBinding],
+    attributes: &[VertexAttribute],
+) -> Result<(), String> {
+    let mut used: Vec<GLuint> = bindings
+        .iter()
+        .flat_map(|binding| binding.attributes.iter())
+        .flat_map(|a| a.column_locations())
+        .map(|(location, _)| location)
+        .collect();
+    for attribute in attributes {
+        for (location, _) in attribute.column_locations() {
+            if used.contains(&location) {
+                return Err(format!("Attribute location {} is used more than once.", location));
+            }
+            used.push(location);
+        }
+    }
+    Ok(())
+}
+
+/// Describes which vertex types are read from which vertex buffer binding
+/// indices, independent of the buffers themselves. A format is applied to a
+/// vertex array once with `VertexArray::set_format`, after which vertex
+/// buffers can be swapped with `VertexArray::bind_vertex_buffer`.
+///
+/// Uses `glVertexAttribFormat` and friends when available. On GL 3.3 the
+/// attribute pointers are respecified whenever a vertex buffer is bound.
+#[derive(Clone, Debug)]
+pub struct VertexFormat {
+    separate: bool,
+    bindings: Vec<VertexBinding>,
+}
+
+impl VertexFormat {
+    pub fn new(capabilities: &Capabilities) -> Self {
+        VertexFormat {
+            separate: capabilities.has_vertex_attrib_binding(),
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Reads the attributes of `V` from binding `index`.
+    pub fn binding<V: Vertex + 'static>(&mut self, index: GLuint) -> Result<&mut Self, String> {
+        if self.bindings.iter().any(|binding| binding.index == index) {
+            return Err(format!("Binding {} is specified more than once.", index));
+        }
+        let attributes = V::attributes();
+        let divisor = binding_divisor(&attributes)?;
+        check_locations(&self.bindings, &attributes)?;
+        self.bindings.push(VertexBinding {
+            index,
+            vertex: TypeId::of::<V>(),
+            stride: mem::size_of::<V>(),
+            divisor,
+            attributes,
+        });
+        Ok(self)
+    }
+
+    fn find_binding<V: Vertex + 'static>(&self, index: GLuint) -> Result<&VertexBinding, String> {
+        let binding = self.bindings
+            .iter()
+            .find(|binding| binding.index == index)
+            .ok_or_else(|| format!("Binding {} is not part of the vertex format.", index))?;
+        if binding.vertex != TypeId::of::<V>() {
+            return Err(format!(
+                "Binding {} expects a buffer of a different vertex type.",
+                index
+            ));
+        }
+        Ok(binding)
+    }
+}
 
 #[derive(Debug)]
 pub struct VertexArrayId(Id);
@@ -25,6 +130,18 @@ impl VertexArrayId {
             gl::BindVertexArray(self.as_uint());
         }
     }
+
+    /// Binds the vertex array and reads the attributes of `V` from `buffer`.
+    /// The vertex array is left bound so an index buffer can be bound to it.
+    pub fn configure<V: Vertex>(&self, buffer: &Buffer<V>) {
+        self.bind();
+        buffer.id().bind(BufferTarget::ArrayBuffer);
+        for attribute in V::attributes() {
+            unsafe {
+                attribute.pointer(mem::size_of::<V>(), 0);
+            }
+        }
+    }
 }
 
 impl Drop for VertexArrayId {
@@ -35,16 +152,127 @@ impl Drop for VertexArrayId {
     }
 }
 
-pub struct VertexArray(VertexArrayId);
+/// A vertex array together with the vertex format applied to it.
+#[derive(Debug)]
+pub struct VertexArray {
+    id: VertexArrayId,
+    format: Option<VertexFormat>,
+}
 
 impl VertexArray {
     pub fn new() -> Result<Self, String> {
-        VertexArrayId::new().map(VertexArray).ok_or_else(|| {
-            String::from("Failed to acquire vertex array id.")
-        })
+        let id = VertexArrayId::new()
+            .ok_or_else(|| String::from("Failed to acquire vertex array id."))?;
+        Ok(VertexArray { id, format: None })
     }
 
     pub fn id(&self) -> &VertexArrayId {
-        &self.0
+        &self.id
+    }
+
+    /// The format applied with `set_format`.
+    #[inline]
+    pub fn format(&self) -> Option<&VertexFormat> {
+        self.format.as_ref()
+    }
+
+    /// See `VertexArrayId::configure`.
+    #[inline]
+    pub fn configure<V: Vertex>(&self, buffer: &Buffer<V>) {
+        self.id.configure(buffer)
+    }
+
+    /// Binds the vertex array and applies `format` to it. Vertex buffers are
+    /// attached with `bind_vertex_buffer` afterwards. The vertex array is
+    /// left bound.
+    pub fn set_format(&mut self, format: VertexFormat) {
+        self.id.bind();
+        if format.separate {
+            for binding in format.bindings.iter() {
+                unsafe {
+                    for attribute in binding.attributes.iter() {
+                        attribute.format(binding.index);
+                    }
+                    gl::VertexBindingDivisor(binding.index, binding.divisor);
+                }
+            }
+        }
+        // Without separate formats the attributes are specified in
+        // `bind_vertex_buffer`.
+        self.format = Some(format);
+    }
+
+    /// Binds the vertex array and reads the vertices for binding `index` of
+    /// its format from `buffer`, starting at vertex `first_vertex`. `V` must
+    /// match the vertex type of the binding. The vertex array is left bound.
+    pub fn bind_vertex_buffer<V: Vertex + 'static>(
+        &self,
+        index: GLuint,
+        buffer: &Buffer<V>,
+        first_vertex: usize,
+    ) -> Result<(), String> {
+        let format = self.format
+            .as_ref()
+            .ok_or_else(|| String::from("No vertex format has been set."))?;
+        let binding = format.find_binding::<V>(index)?;
+        let offset = first_vertex * binding.stride;
+        self.id.bind();
+        unsafe {
+            if format.separate {
+                gl::BindVertexBuffer(
+                    index,
+                    buffer.id().as_uint(),
+                    offset as GLintptr,
+                    binding.stride as GLsizei,
+                );
+            } else {
+                buffer.id().bind(BufferTarget::ArrayBuffer);
+                for attribute in binding.attributes.iter() {
+                    attribute.pointer(binding.stride, offset);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vertex::AttributeType;
+
+    fn attribute(location: GLuint, columns: GLuint, divisor: GLuint) -> VertexAttribute {
+        VertexAttribute {
+            location,
+            components: 4,
+            component_type: AttributeType::Float,
+            columns,
+            normalized: false,
+            integer: false,
+            offset: 0,
+            divisor,
+        }
+    }
+
+    #[test]
+    fn bindings_share_a_divisor() {
+        assert_eq!(binding_divisor(&[]), Ok(0));
+        assert_eq!(binding_divisor(&[attribute(3, 4, 1), attribute(7, 1, 1)]), Ok(1));
+        assert!(binding_divisor(&[attribute(0, 1, 0), attribute(3, 4, 1)]).is_err());
+    }
+
+    #[test]
+    fn locations_are_not_reused() {
+        let bindings = vec![VertexBinding {
+            index: 0,
+            vertex: TypeId::of::<()>(),
+            stride: 64,
+            divisor: 1,
+            attributes: vec![attribute(3, 4, 1)],
+        }];
+        assert!(check_locations(&bindings, &[attribute(0, 1, 0), attribute(7, 1, 0)]).is_ok());
+        assert!(check_locations(&bindings, &[attribute(6, 1, 0)]).is_err());
+        assert!(check_locations(&[], &[attribute(0, 1, 0), attribute(0, 1, 0)]).is_err());
+        assert!(check_locations(&[], &[attribute(0, 4, 0), attribute(2, 1, 0)]).is_err());
     }
 }
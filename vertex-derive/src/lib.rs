@@ -0,0 +1,162 @@
+//! Implements `vertex::Vertex` for structs whose fields are marked with
+//! `#[vertex(location = N)]`. Fields can additionally be marked `normalized`
+//! to map integers to [0, 1] or [-1, 1], or `integer` to pass them to the
+//! shader as integers. Per-instance fields take a `divisor = N`. Fields
+//! without the attribute are ignored. Marking a field whose type does not
+//! implement `vertex::IntegerAttributeFormat` as `integer` fails to compile.
+//!
+//! The generated code refers to `::vertex` and `field_offset!`, so it can only
+//! be used in the crate that defines the `vertex` module and imports
+//! `simple-field-offset`.
+//!
+//! ```ignore
+//! #[derive(Clone, Copy, Vertex)]
+//! #[repr(C)]
+//! struct PostVertex {
+//!     #[vertex(location = 0)]
+//!     position: [GLfloat; 2],
+//!     #[vertex(location = 1, normalized)]
+//!     color: [u8; 4],
+//! }
+//!
+//! #[derive(Clone, Copy, Vertex)]
+//! #[repr(C)]
+//! struct Instance {
+//!     // Occupies locations 2 through 5, one per column.
+//!     #[vertex(location = 2, divisor = 1)]
+//!     pos_from_obj_to_wld_space: Matrix4<GLfloat>,
+//! }
+//! ```
+
+extern crate proc_macro;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use syn::{Attribute, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+struct AttributeOptions {
+    location: u32,
+    normalized: bool,
+    integer: bool,
+    divisor: u32,
+}
+
+fn parse_options(attrs: &[Attribute]) -> Option<AttributeOptions> {
+    let list = attrs
+        .iter()
+        .filter_map(|attr| match attr.interpret_meta() {
+            Some(Meta::List(ref list)) if list.ident == "vertex" => Some(list.clone()),
+            _ => None,
+        })
+        .next()?;
+
+    let mut location = None;
+    let mut normalized = false;
+    let mut integer = false;
+    let mut divisor = 0;
+
+    for nested in list.nested.iter() {
+        match *nested {
+            NestedMeta::Meta(Meta::NameValue(ref name_value)) if name_value.ident == "location" => {
+                match name_value.lit {
+                    Lit::Int(ref value) => location = Some(value.value() as u32),
+                    _ => panic!("#[vertex(location = N)] expects an integer location."),
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(ref name_value)) if name_value.ident == "divisor" => {
+                match name_value.lit {
+                    Lit::Int(ref value) => divisor = value.value() as u32,
+                    _ => panic!("#[vertex(divisor = N)] expects an integer divisor."),
+                }
+            }
+            NestedMeta::Meta(Meta::Word(ref word)) if word == "normalized" => normalized = true,
+            NestedMeta::Meta(Meta::Word(ref word)) if word == "integer" => integer = true,
+            _ => panic!(
+                "Unknown #[vertex] option, expected location, divisor, normalized or integer."
+            ),
+        }
+    }
+
+    if normalized && integer {
+        panic!("#[vertex] options normalized and integer are mutually exclusive.");
+    }
+
+    Some(AttributeOptions {
+        location: location.expect("#[vertex] requires a location."),
+        normalized,
+        integer,
+        divisor,
+    })
+}
+
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).expect("Failed to parse #[derive(Vertex)] input.");
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        panic!("#[derive(Vertex)] does not support generic structs.");
+    }
+
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => panic!("#[derive(Vertex)] requires a struct with named fields."),
+        },
+        _ => panic!("#[derive(Vertex)] can only be used on structs."),
+    };
+
+    let integer_types: Vec<_> = fields
+        .iter()
+        .filter(|field| parse_options(&field.attrs).map_or(false, |options| options.integer))
+        .map(|field| &field.ty)
+        .collect();
+
+    // Fails to compile when an `integer` field has floating point components.
+    let integer_assertions = if integer_types.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn assert_integer<T: ::vertex::IntegerAttributeFormat>() {}
+            #(assert_integer::<#integer_types>();)*
+        }
+    };
+
+    let attributes: Vec<_> = fields
+        .iter()
+        .filter_map(|field| {
+            let options = parse_options(&field.attrs)?;
+            let ident = field.ident.as_ref().unwrap();
+            let ty = &field.ty;
+            let location = options.location;
+            let normalized = options.normalized;
+            let integer = options.integer;
+            let divisor = options.divisor;
+            Some(quote! {
+                ::vertex::VertexAttribute {
+                    location: #location,
+                    components: <#ty as ::vertex::AttributeFormat>::COMPONENTS,
+                    component_type: <#ty as ::vertex::AttributeFormat>::COMPONENT_TYPE,
+                    columns: <#ty as ::vertex::AttributeFormat>::COLUMNS,
+                    normalized: #normalized,
+                    integer: #integer,
+                    offset: field_offset!(#name, #ident),
+                    divisor: #divisor,
+                }
+            })
+        })
+        .collect();
+
+    let expanded = quote! {
+        unsafe impl ::vertex::Vertex for #name {
+            fn attributes() -> Vec<::vertex::VertexAttribute> {
+                #integer_assertions
+                vec![#(#attributes),*]
+            }
+        }
+    };
+
+    expanded.into()
+}